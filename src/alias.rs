@@ -0,0 +1,132 @@
+use crate::capabilities::DeviceCapabilities;
+use crate::error::{AppError, Error};
+use crate::query::{DeviceQuery, DeviceStatus};
+use std::fmt::Display;
+
+/// A stable name for a device, resolved through a [DeviceQuery].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceAlias {
+    pub(crate) name: String,
+    pub(crate) query: DeviceQuery,
+    pub(crate) device_id: Option<u32>,
+}
+
+impl DeviceAlias {
+    #[profiling::function]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[profiling::function]
+    pub fn query(&self) -> &DeviceQuery {
+        &self.query
+    }
+
+    /// Resolved device or `None` if no device matched.
+    #[profiling::function]
+    pub fn device_id(&self) -> Option<u32> {
+        self.device_id
+    }
+}
+
+impl Display for DeviceAlias {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.device_id {
+            Some(device_id) => f.write_fmt(format_args!("{} -> Device {}", self.name, device_id)),
+            None => f.write_fmt(format_args!("{} -> no match", self.name)),
+        }
+    }
+}
+
+/// Logical devices by name, e.g. "stick", "throttle" and "pedals", so applications survive reconfigured device IDs.
+///
+/// Aliases are resolved in registration order. Each alias gets the best match of its query that no earlier alias claimed,
+/// so two aliases with similar queries map to two different devices.
+/// [VJoy](crate::VJoy) keeps a registry that resolves against its owned devices, see [VJoy::register_alias](crate::VJoy::register_alias).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceAliases {
+    aliases: Vec<DeviceAlias>,
+}
+
+impl DeviceAliases {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an alias or replaces the query of an existing one, keeping its position. Call [resolve](Self::resolve) afterwards.
+    #[profiling::function]
+    pub fn register<S: Into<String>>(&mut self, name: S, query: DeviceQuery) {
+        let name = name.into();
+        match self.aliases.iter_mut().find(|alias| alias.name == name) {
+            Some(alias) => alias.query = query,
+            None => self.aliases.push(DeviceAlias {
+                name,
+                query,
+                device_id: None,
+            }),
+        }
+    }
+
+    /// Removes an alias and returns its query. Call [resolve](Self::resolve) afterwards.
+    #[profiling::function]
+    pub fn unregister(&mut self, name: &str) -> Option<DeviceQuery> {
+        let index = self.aliases.iter().position(|alias| alias.name == name)?;
+        Some(self.aliases.remove(index).query)
+    }
+
+    /// Assigns devices to all aliases. Candidates are treated as owned devices.
+    #[profiling::function]
+    pub fn resolve(&mut self, candidates: &[DeviceCapabilities]) {
+        let mut claimed: Vec<u32> = Vec::new();
+        for alias in &mut self.aliases {
+            let free = candidates
+                .iter()
+                .filter(|capabilities| !claimed.contains(&capabilities.device_id()))
+                .map(|capabilities| (capabilities.clone(), DeviceStatus::Owned));
+
+            alias.device_id = alias
+                .query
+                .rank(free)
+                .first()
+                .map(|candidate| candidate.device_id());
+            claimed.extend(alias.device_id);
+        }
+    }
+
+    #[profiling::function]
+    pub fn get(&self, name: &str) -> Option<&DeviceAlias> {
+        self.aliases.iter().find(|alias| alias.name == name)
+    }
+
+    /// Device ID of the alias.
+    #[profiling::function]
+    pub fn device_id(&self, name: &str) -> Result<u32, Error> {
+        let Some(alias) = self.get(name) else {
+            return Err(Error::App(AppError::AliasNotFound(name.to_string())));
+        };
+
+        alias.device_id.ok_or_else(|| {
+            Error::App(AppError::AliasNotMatched(
+                alias.name.clone(),
+                alias.query.to_string(),
+            ))
+        })
+    }
+
+    #[profiling::function]
+    pub fn iter(&self) -> std::slice::Iter<'_, DeviceAlias> {
+        self.aliases.iter()
+    }
+
+    #[profiling::function]
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    #[profiling::function]
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Current state of an enabled device axis.
 ///
 /// Range of IDs is 1..=8 for consistency with the .dll.
@@ -13,6 +14,13 @@ pub struct Axis {
 }
 
 impl Axis {
+    /// Lowest value the driver accepts for an axis.
+    pub const MIN: i32 = 0;
+    /// Highest value the driver accepts for an axis, so the range is symmetric around [CENTER](Self::CENTER).
+    pub const MAX: i32 = 0x8000;
+    /// Value of a centered axis.
+    pub const CENTER: i32 = 0x4000;
+
     #[profiling::function]
     pub fn get(&self) -> i32 {
         self.value
@@ -23,11 +31,46 @@ impl Axis {
         self.value = value;
     }
 
+    /// Sets the value from the range -1.0..=1.0, the inverse of [normalized](Self::normalized). Values outside of the range are clamped.
+    #[profiling::function]
+    pub fn set_normalized(&mut self, value: f32) {
+        self.value = Self::from_normalized(value);
+    }
+
+    /// Raw value for a value in the range -1.0..=1.0, see [set_normalized](Self::set_normalized).
+    #[profiling::function]
+    pub fn from_normalized(value: f32) -> i32 {
+        let value = value.clamp(-1.0, 1.0);
+        let range = if value >= 0.0 {
+            Self::MAX - Self::CENTER
+        } else {
+            Self::CENTER - Self::MIN
+        };
+        Self::CENTER + (value * range as f32).round() as i32
+    }
+
+    /// Value mapped to the range -1.0..=1.0, with [CENTER](Self::CENTER) at 0.0.
+    #[profiling::function]
+    pub fn normalized(&self) -> f32 {
+        let value = self.value.clamp(Self::MIN, Self::MAX);
+        if value >= Self::CENTER {
+            (value - Self::CENTER) as f32 / (Self::MAX - Self::CENTER) as f32
+        } else {
+            (value - Self::CENTER) as f32 / (Self::CENTER - Self::MIN) as f32
+        }
+    }
+
     #[profiling::function]
     pub fn reset(&mut self) {
         self.value = i32::default();
     }
 
+    /// Kind of the axis or `None` if its HID usage is unknown.
+    #[profiling::function]
+    pub fn kind(&self) -> Option<AxisKind> {
+        AxisKind::from_hid_usage(self.hid_usage)
+    }
+
     #[profiling::function]
     pub fn display_name(&mut self) -> String {
         self.display_name.clone()
@@ -39,6 +82,76 @@ impl Axis {
     }
 }
 
+/// The 8 axes vJoy supports, in the order of their IDs 1..=8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AxisKind {
+    X,
+    Y,
+    Z,
+    Rx,
+    Ry,
+    Rz,
+    Slider,
+    Dial,
+}
+
+impl AxisKind {
+    pub const ALL: [AxisKind; 8] = [
+        AxisKind::X,
+        AxisKind::Y,
+        AxisKind::Z,
+        AxisKind::Rx,
+        AxisKind::Ry,
+        AxisKind::Rz,
+        AxisKind::Slider,
+        AxisKind::Dial,
+    ];
+
+    /// Axis ID in the range 1..=8.
+    #[profiling::function]
+    pub fn id(&self) -> u32 {
+        *self as u32 + 1
+    }
+
+    #[profiling::function]
+    pub fn from_id(axis_id: u32) -> Option<Self> {
+        Self::ALL.get((axis_id as usize).checked_sub(1)?).copied()
+    }
+
+    /// HID usage of the axis on the generic desktop page, e.g. 0x30 for X.
+    #[profiling::function]
+    pub fn hid_usage(&self) -> u32 {
+        0x30 + *self as u32
+    }
+
+    #[profiling::function]
+    pub fn from_hid_usage(hid_usage: u32) -> Option<Self> {
+        Self::from_id(hid_usage.checked_sub(0x30)? + 1)
+    }
+
+    /// Display name as reported by the driver.
+    #[profiling::function]
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            AxisKind::X => "X",
+            AxisKind::Y => "Y",
+            AxisKind::Z => "Z",
+            AxisKind::Rx => "Rx",
+            AxisKind::Ry => "Ry",
+            AxisKind::Rz => "Rz",
+            AxisKind::Slider => "Slider",
+            AxisKind::Dial => "Dial/Slider2",
+        }
+    }
+}
+
+impl Display for AxisKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.display_name()))
+    }
+}
+
 impl Display for Axis {
     #[profiling::function]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
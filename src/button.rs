@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonState {
     #[default]
     Released = 0,
@@ -22,7 +23,8 @@ impl Display for ButtonState {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Current state of an enabled device button.
 ///
 /// Range of IDs is 1..=128 for consistency with the .dll.
@@ -0,0 +1,181 @@
+use crate::axis::AxisKind;
+use crate::device::Device;
+use crate::error::{AppError, Error};
+use crate::hat::HatType;
+use std::fmt::Display;
+
+/// Static layout of a vJoy device as configured in vJoyConf: button count, enabled axes and hat types.
+///
+/// Unlike a [Device], capabilities hold no live state. They are cached per device by [VJoy::capabilities](crate::VJoy::capabilities),
+/// can be built for a profile via [new](Self::new), and check whether a device or another layout fits them.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, AxisKind, DeviceCapabilities, Error, HatType};
+/// # let vjoy = VJoy::from_default_dll_location()?;
+/// let required = DeviceCapabilities::new(1, 16, vec![AxisKind::X, AxisKind::Y], vec![HatType::Discrete]);
+/// vjoy.capabilities(1)?.satisfies(&required)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceCapabilities {
+    pub(crate) device_id: u32,
+    pub(crate) num_buttons: u32,
+    pub(crate) axes: Vec<AxisKind>,
+    pub(crate) hats: Vec<HatType>,
+}
+
+impl DeviceCapabilities {
+    /// Axes are sorted by ID and deduplicated.
+    #[profiling::function]
+    pub fn new(
+        device_id: u32,
+        num_buttons: u32,
+        mut axes: Vec<AxisKind>,
+        hats: Vec<HatType>,
+    ) -> Self {
+        axes.sort_unstable();
+        axes.dedup();
+
+        Self {
+            device_id,
+            num_buttons,
+            axes,
+            hats,
+        }
+    }
+
+    /// Capabilities of a device. Axes with an unknown HID usage are skipped.
+    #[profiling::function]
+    pub fn from_device(device: &Device) -> Self {
+        Self::new(
+            device.id,
+            device.buttons.len() as u32,
+            device.axes.iter().filter_map(|axis| axis.kind()).collect(),
+            device.hats.iter().map(|hat| hat.hat_type).collect(),
+        )
+    }
+
+    #[profiling::function]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    #[profiling::function]
+    pub fn num_buttons(&self) -> u32 {
+        self.num_buttons
+    }
+
+    #[profiling::function]
+    pub fn axes(&self) -> &[AxisKind] {
+        &self.axes
+    }
+
+    #[profiling::function]
+    pub fn has_axis(&self, kind: AxisKind) -> bool {
+        self.axes.contains(&kind)
+    }
+
+    #[profiling::function]
+    pub fn num_axes(&self) -> usize {
+        self.axes.len()
+    }
+
+    /// Types of all hats, ordered by hat ID.
+    #[profiling::function]
+    pub fn hats(&self) -> &[HatType] {
+        &self.hats
+    }
+
+    #[profiling::function]
+    pub fn num_hats(&self) -> usize {
+        self.hats.len()
+    }
+
+    /// Checks that the device has this ID and exactly this layout, e.g. before applying a saved device state.
+    #[profiling::function]
+    pub fn validate(&self, device: &Device) -> Result<(), Error> {
+        if device.id != self.device_id {
+            return Err(self.mismatch(format!("device ID is {}", device.id)));
+        }
+
+        self.validate_layout(device)
+    }
+
+    /// Checks that the device has exactly this layout. Unlike [validate](Self::validate), the device ID is ignored.
+    #[profiling::function]
+    pub fn validate_layout(&self, device: &Device) -> Result<(), Error> {
+        let actual = Self {
+            device_id: self.device_id,
+            ..Self::from_device(device)
+        };
+        if actual.axes.len() != device.axes.len() {
+            return Err(self.mismatch("device has axes with unknown HID usages".to_string()));
+        }
+        if actual != *self {
+            return Err(self.mismatch(format!("device layout is {}", actual)));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that this layout provides at least the buttons, axes and hats of `required`, e.g. those a profile uses.
+    ///
+    /// Hats are compared by position, so hat N of `required` needs a hat N of the same type. The device ID is ignored.
+    #[profiling::function]
+    pub fn satisfies(&self, required: &DeviceCapabilities) -> Result<(), Error> {
+        if self.num_buttons < required.num_buttons {
+            return Err(self.mismatch(format!(
+                "{} buttons required, {} available",
+                required.num_buttons, self.num_buttons
+            )));
+        }
+
+        if let Some(missing) = required.axes.iter().find(|kind| !self.has_axis(**kind)) {
+            return Err(self.mismatch(format!("axis {} is missing", missing)));
+        }
+
+        for (index, hat_type) in required.hats.iter().enumerate() {
+            match self.hats.get(index) {
+                Some(available) if available == hat_type => {}
+                Some(available) => {
+                    return Err(self.mismatch(format!(
+                        "hat {} is a {} hat, {} required",
+                        index + 1,
+                        available,
+                        hat_type
+                    )));
+                }
+                None => {
+                    return Err(self.mismatch(format!("hat {} is missing", index + 1)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mismatch(&self, reason: String) -> Error {
+        Error::App(AppError::CapabilityMismatch(self.device_id, reason))
+    }
+}
+
+impl Display for DeviceCapabilities {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let axes: Vec<&str> = self.axes.iter().map(|kind| kind.display_name()).collect();
+        let hats: Vec<String> = self
+            .hats
+            .iter()
+            .map(|hat_type| hat_type.to_string())
+            .collect();
+
+        f.write_fmt(format_args!(
+            "Device ID: {} | buttons: {} | axes: [{}] | hats: [{}]",
+            self.device_id,
+            self.num_buttons,
+            axes.join(", "),
+            hats.join(", ")
+        ))
+    }
+}
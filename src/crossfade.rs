@@ -0,0 +1,178 @@
+use crate::device::Device;
+use crate::error::Error;
+use std::time::Duration;
+
+/// Timed transition of a device from one state to another, see [Device::lerp_with_threshold].
+///
+/// Times are measured on the caller's clock, e.g. the time elapsed since the application started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crossfade {
+    from: Device,
+    to: Device,
+    start: Duration,
+    duration: Duration,
+    threshold: f32,
+}
+
+impl Crossfade {
+    /// Crossfade from `from` to `to`, starting at `start`. Both states need the same layout.
+    ///
+    /// Buttons and discrete hats switch halfway, see [with_threshold](Self::with_threshold).
+    #[profiling::function]
+    pub fn new(
+        from: Device,
+        to: Device,
+        start: Duration,
+        duration: Duration,
+    ) -> Result<Self, Error> {
+        from.capabilities().validate(&to)?;
+
+        Ok(Self {
+            from,
+            to,
+            start,
+            duration,
+            threshold: 0.5,
+        })
+    }
+
+    /// Progress in the range 0.0..=1.0 at which buttons and discrete hats switch to the target state.
+    #[profiling::function]
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    #[profiling::function]
+    pub fn device_id(&self) -> u32 {
+        self.to.id
+    }
+
+    #[profiling::function]
+    pub fn target(&self) -> &Device {
+        &self.to
+    }
+
+    /// Progress in the range 0.0..=1.0 at `now`. A crossfade with a duration of zero is finished immediately.
+    #[profiling::function]
+    pub fn progress(&self, now: Duration) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        let elapsed = now.saturating_sub(self.start);
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0) as f32
+    }
+
+    #[profiling::function]
+    pub fn is_finished(&self, now: Duration) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Interpolated device state at `now`.
+    #[profiling::function]
+    pub fn state(&self, now: Duration) -> Result<Device, Error> {
+        Device::lerp_with_threshold(&self.from, &self.to, self.progress(now), self.threshold)
+    }
+}
+
+/// Runs crossfades for multiple devices, at most one per device.
+///
+/// Call [update](Self::update) once per frame with the cached devices of [VJoy](crate::VJoy) and send them afterwards.
+/// Crossfades are removed once they are finished.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, CrossfadeScheduler, Error};
+/// # use std::time::{Duration, Instant};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// # let target = vjoy.get_device_state(1)?;
+/// let clock = Instant::now();
+/// let mut scheduler = CrossfadeScheduler::new();
+/// scheduler.start(vjoy.get_device_state_ref(1)?, target, clock.elapsed(), Duration::from_millis(500))?;
+///
+/// while scheduler.is_active(1) {
+///     scheduler.update(vjoy.devices_mut(), clock.elapsed())?;
+///     vjoy.update_all_devices()?;
+/// }
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CrossfadeScheduler {
+    crossfades: Vec<Crossfade>,
+}
+
+impl CrossfadeScheduler {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a crossfade from the current state of a device to `target`.
+    ///
+    /// A running crossfade of the device is replaced. Pass the current, partially faded state as `from` to avoid a jump.
+    #[profiling::function]
+    pub fn start(
+        &mut self,
+        from: &Device,
+        target: Device,
+        now: Duration,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let crossfade = Crossfade::new(from.clone(), target, now, duration)?;
+        self.schedule(crossfade);
+
+        Ok(())
+    }
+
+    /// Adds a crossfade, replacing a running crossfade of the same device.
+    #[profiling::function]
+    pub fn schedule(&mut self, crossfade: Crossfade) {
+        self.cancel(crossfade.device_id());
+        self.crossfades.push(crossfade);
+    }
+
+    /// Stops the crossfade of a device, leaving it at its last updated state.
+    #[profiling::function]
+    pub fn cancel(&mut self, device_id: u32) -> Option<Crossfade> {
+        let index = self
+            .crossfades
+            .iter()
+            .position(|crossfade| crossfade.device_id() == device_id)?;
+        Some(self.crossfades.remove(index))
+    }
+
+    #[profiling::function]
+    pub fn is_active(&self, device_id: u32) -> bool {
+        self.crossfades
+            .iter()
+            .any(|crossfade| crossfade.device_id() == device_id)
+    }
+
+    #[profiling::function]
+    pub fn is_empty(&self) -> bool {
+        self.crossfades.is_empty()
+    }
+
+    /// Sets each device with a running crossfade to its interpolated state at `now`. Other devices are left untouched.
+    #[profiling::function]
+    pub fn update<'a, I>(&mut self, devices: I, now: Duration) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = &'a mut Device>,
+    {
+        for device in devices {
+            let Some(crossfade) = self
+                .crossfades
+                .iter()
+                .find(|crossfade| crossfade.device_id() == device.id)
+            else {
+                continue;
+            };
+
+            *device = crossfade.state(now)?;
+        }
+        self.crossfades
+            .retain(|crossfade| !crossfade.is_finished(now));
+
+        Ok(())
+    }
+}
@@ -1,13 +1,15 @@
-use crate::FourWayHat;
-use crate::axis::Axis;
+use crate::axis::{Axis, AxisKind};
 use crate::button::{Button, ButtonState};
+use crate::capabilities::DeviceCapabilities;
 use crate::error::{AppError, Error};
-use crate::hat::{Hat, HatState};
+use crate::hat::{EightWayHat, Hat, HatState, HatType};
+use crate::merge::{AxisMerge, ButtonMerge, HatMerge, MergePolicy, MergeSource};
 use std::fmt::Display;
 use std::slice::Iter;
 use std::slice::IterMut;
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Current state of an enabled vJoy device.
 ///
 /// Range of IDs is 1..=16 for consistency with the .dll.
@@ -44,6 +46,9 @@ use std::slice::IterMut;
 /// # Ok::<(), Error>(())
 /// ```
 ///  
+/// A device tracks whether it was modified since it was last sent to the driver, see [is_dirty](Self::is_dirty).
+/// [VJoy::update_all_devices](crate::VJoy::update_all_devices) only sends dirty devices.
+///
 /// **To reset all axes and all buttons for device 1**:
 ///
 /// ```no_run
@@ -59,16 +64,32 @@ pub struct Device {
     pub(crate) buttons: Vec<Button>,
     pub(crate) axes: Vec<Axis>,
     pub(crate) hats: Vec<Hat>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) dirty: bool,
 }
 
+// Dirty tracking is bookkeeping and not part of the device state
+impl PartialEq for Device {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.buttons == other.buttons
+            && self.axes == other.axes
+            && self.hats == other.hats
+    }
+}
+
+impl Eq for Device {}
+
 impl Device {
     #[profiling::function]
     pub fn id(&self) -> u32 {
         self.id
     }
 
+    /// Marks the device as dirty, since changes via the iterator can't be tracked individually.
     #[profiling::function]
     pub fn buttons_mut(&mut self) -> IterMut<'_, Button> {
+        self.dirty = true;
         self.buttons.iter_mut()
     }
 
@@ -84,6 +105,7 @@ impl Device {
 
     #[profiling::function]
     pub fn axes_mut(&mut self) -> IterMut<'_, Axis> {
+        self.dirty = true;
         self.axes.iter_mut()
     }
 
@@ -99,6 +121,7 @@ impl Device {
 
     #[profiling::function]
     pub fn hats_mut(&mut self) -> IterMut<'_, Hat> {
+        self.dirty = true;
         self.hats.iter_mut()
     }
 
@@ -112,13 +135,40 @@ impl Device {
         self.hats.len()
     }
 
+    /// Static layout of the device. [VJoy::capabilities](crate::VJoy::capabilities) returns a cached copy.
     #[profiling::function]
-    pub fn hat_type(&self) -> HatState {
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::from_device(self)
+    }
+
+    /// Configured type of the first hat or [HatType::Discrete] if the device has no hats.
+    ///
+    /// Use [Hat::hat_type] for devices with mixed hat types.
+    #[profiling::function]
+    pub fn hat_type(&self) -> HatType {
         let Some(hat) = self.hats.first() else {
-            return HatState::Discrete(FourWayHat::Centered);
+            return HatType::Discrete;
         };
 
-        hat.state
+        hat.hat_type
+    }
+
+    /// Hat values as sent to the driver, for hat IDs 1..=4.
+    ///
+    /// Discrete hats are sent as their [FourWayHat](crate::FourWayHat) value and continuous hats in 1/100°.
+    /// Absent hats and hats with a state that doesn't match their type are sent as centered (`u32::MAX`).
+    #[profiling::function]
+    pub fn raw_hats(&self) -> [u32; 4] {
+        let mut raw = [u32::MAX; 4];
+        for (value, hat) in raw.iter_mut().zip(&self.hats) {
+            *value = match (hat.hat_type, hat.state) {
+                (HatType::Continuous, HatState::Continuous(c)) => c,
+                (HatType::Discrete, HatState::Discrete(d)) => d as u32,
+                _ => u32::MAX,
+            };
+        }
+
+        raw
     }
 
     #[profiling::function]
@@ -133,6 +183,7 @@ impl Device {
         };
 
         button.set(state);
+        self.dirty = true;
 
         Ok(())
     }
@@ -148,11 +199,44 @@ impl Device {
             None => return Err(Error::App(AppError::HatNotFound(self.id, hat_id))),
         };
 
+        if hat.hat_type != state.hat_type() {
+            return Err(Error::App(AppError::HatTypeMismatch(
+                self.id,
+                hat_id,
+                hat.hat_type,
+                state,
+            )));
+        }
+
         hat.set(state);
+        self.dirty = true;
 
         Ok(())
     }
 
+    /// Sets a hat from an analog vector. See [Hat::set_from_vector].
+    #[profiling::function]
+    pub fn set_hat_from_vector(
+        &mut self,
+        hat_id: u8,
+        x: f32,
+        y: f32,
+        deadzone: f32,
+    ) -> Result<(), Error> {
+        let state = match self.hats.get((hat_id as usize).wrapping_sub(1)) {
+            Some(hat) => hat.state_from_vector(x, y, deadzone),
+            None => return Err(Error::App(AppError::HatNotFound(self.id, hat_id))),
+        };
+
+        self.set_hat(hat_id, state)
+    }
+
+    /// Sets a continuous hat to an 8-way direction. Discrete hats are rejected with [AppError::HatTypeMismatch].
+    #[profiling::function]
+    pub fn set_hat_eight_way(&mut self, hat_id: u8, direction: EightWayHat) -> Result<(), Error> {
+        self.set_hat(hat_id, HatState::from(direction))
+    }
+
     #[profiling::function]
     pub fn set_axis(&mut self, axis_id: u32, value: i32) -> Result<(), Error> {
         if axis_id == 0 {
@@ -165,6 +249,7 @@ impl Device {
         };
 
         axis.set(value);
+        self.dirty = true;
 
         Ok(())
     }
@@ -180,16 +265,429 @@ impl Device {
         for hat in &mut self.hats {
             hat.reset();
         }
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Resets all components to the state the driver resets a device to: buttons released, hats centered,
+    /// the X, Y and Z axes centered and all other axes at 0.
+    #[profiling::function]
+    pub(crate) fn reset_to_driver_defaults(&mut self) {
+        for button in &mut self.buttons {
+            button.reset();
+        }
+        for hat in &mut self.hats {
+            hat.reset();
+        }
+        for axis in &mut self.axes {
+            axis.value = match axis.kind() {
+                Some(AxisKind::X | AxisKind::Y | AxisKind::Z) => Axis::CENTER,
+                _ => Axis::MIN,
+            };
+        }
+        self.dirty = true;
+    }
+
+    /// Whether the device was modified since it was last sent to the driver.
+    ///
+    /// Dirty tracking is conservative: setting a component to its current value or borrowing a mutable iterator marks the device dirty as well.
+    #[profiling::function]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Forces the device to be sent on the next [VJoy::update_all_devices](crate::VJoy::update_all_devices), e.g. after another feeder changed it.
+    #[profiling::function]
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Components whose state differs from `previous`, with their state in `self`.
+    ///
+    /// Components are matched by position. Components that `previous` lacks are always part of the diff.
+    #[profiling::function]
+    pub fn diff(&self, previous: &Device) -> DeviceDiff {
+        DeviceDiff {
+            device_id: self.id,
+            buttons: self
+                .buttons
+                .iter()
+                .enumerate()
+                .filter(|(index, button)| previous.buttons.get(*index) != Some(button))
+                .map(|(_, button)| (button.id, button.state))
+                .collect(),
+            axes: self
+                .axes
+                .iter()
+                .enumerate()
+                .filter(|(index, axis)| {
+                    previous.axes.get(*index).map(|previous| previous.value) != Some(axis.value)
+                })
+                .map(|(index, axis)| (index as u32 + 1, axis.value))
+                .collect(),
+            hats: self
+                .hats
+                .iter()
+                .enumerate()
+                .filter(|(index, hat)| previous.hats.get(*index) != Some(hat))
+                .map(|(_, hat)| (hat.id, hat.state))
+                .collect(),
+        }
+    }
+
+    /// Interpolates between two states of the same device, see [lerp_with_threshold](Self::lerp_with_threshold).
+    ///
+    /// Buttons and discrete hats switch to `b` at `t >= 0.5`.
+    #[profiling::function]
+    pub fn lerp(a: &Device, b: &Device, t: f32) -> Result<Device, Error> {
+        Self::lerp_with_threshold(a, b, t, 0.5)
+    }
+
+    /// State at `t` in the range 0.0..=1.0 between `a` and `b`. Values outside of this range are clamped.
+    ///
+    /// Axes interpolate linearly and continuous hats along the shorter arc.
+    /// Buttons, discrete hats and continuous hats that are centered in `a` or `b` switch to their state in `b` at `t >= threshold`.
+    /// Both states need the same layout.
+    #[profiling::function]
+    pub fn lerp_with_threshold(
+        a: &Device,
+        b: &Device,
+        t: f32,
+        threshold: f32,
+    ) -> Result<Device, Error> {
+        a.capabilities().validate(b)?;
+
+        let t = t.clamp(0.0, 1.0);
+        let switched = t >= threshold;
+
+        let mut device = a.clone();
+        if switched {
+            for (button, target) in device.buttons.iter_mut().zip(&b.buttons) {
+                button.state = target.state;
+            }
+        }
+        for (axis, target) in device.axes.iter_mut().zip(&b.axes) {
+            let delta = (target.value as f64 - axis.value as f64) * t as f64;
+            axis.value = (axis.value as f64 + delta).round() as i32;
+        }
+        for (hat, target) in device.hats.iter_mut().zip(&b.hats) {
+            hat.state = match (hat.state, target.state) {
+                (HatState::Continuous(_), HatState::Continuous(_)) => {
+                    match (hat.state.angle(), target.state.angle()) {
+                        (Some(from), Some(to)) => {
+                            let from = from.hundredths() as f32;
+                            let delta = (to.hundredths() as f32 - from + 18000.0)
+                                .rem_euclid(36000.0)
+                                - 18000.0;
+                            let angle = (from + delta * t).round().rem_euclid(36000.0) as u32;
+                            HatState::Continuous(angle % 36000)
+                        }
+                        _ if switched => target.state,
+                        _ => hat.state,
+                    }
+                }
+                _ if switched => target.state,
+                _ => hat.state,
+            };
+        }
+        device.dirty = true;
+
+        Ok(device)
+    }
+
+    /// Sets this device to the combined state of multiple sources, e.g. to feed one vJoy device from several physical devices.
+    ///
+    /// Components are combined per [MergePolicy]. All sources need the layout of this device, their device IDs may differ.
+    /// Without sources, the device is left unchanged.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, Error, MergePolicy, MergeSource};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// # let (hotas, keyboard) = (vjoy.get_device_state(1)?, vjoy.get_device_state(1)?);
+    /// let sources = [MergeSource::new(&hotas), MergeSource::new(&keyboard).priority(1)];
+    /// vjoy.get_device_state_mut(1)?.merge(&sources, MergePolicy::default())?;
+    /// vjoy.update_all_devices()?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn merge(&mut self, sources: &[MergeSource], policy: MergePolicy) -> Result<(), Error> {
+        let capabilities = self.capabilities();
+        for source in sources {
+            capabilities.validate_layout(source.device)?;
+        }
+        let Some(prioritized) = first_max_by_key(sources.iter(), |source| source.priority) else {
+            return Ok(());
+        };
+
+        for (index, button) in self.buttons.iter_mut().enumerate() {
+            button.state = match policy.buttons {
+                ButtonMerge::Or => {
+                    let pressed = sources
+                        .iter()
+                        .any(|source| source.device.buttons[index].state == ButtonState::Pressed);
+                    if pressed {
+                        ButtonState::Pressed
+                    } else {
+                        ButtonState::Released
+                    }
+                }
+                ButtonMerge::Priority => prioritized.device.buttons[index].state,
+            };
+        }
+
+        for (index, axis) in self.axes.iter_mut().enumerate() {
+            let values = sources
+                .iter()
+                .map(|source| source.device.axes[index].value as i64);
+            let center = Axis::CENTER as i64;
+
+            axis.value = match policy.axes {
+                AxisMerge::MaxDeflection => {
+                    first_max_by_key(values, |value| (value - center).abs()).unwrap_or(center)
+                }
+                AxisMerge::SumClamp => {
+                    let deflection: i64 = values.map(|value| value - center).sum();
+                    (center + deflection).clamp(Axis::MIN as i64, Axis::MAX as i64)
+                }
+                AxisMerge::Average => {
+                    (values.sum::<i64>() as f64 / sources.len() as f64).round() as i64
+                }
+                AxisMerge::Priority => prioritized.device.axes[index].value as i64,
+            } as i32;
+        }
+
+        for (index, hat) in self.hats.iter_mut().enumerate() {
+            let active = sources
+                .iter()
+                .filter(|source| source.device.hats[index].state.angle().is_some());
+            let source = match policy.hats {
+                HatMerge::Priority => first_max_by_key(active, |source| source.priority),
+                HatMerge::MostRecent => first_max_by_key(active, |source| source.updated),
+            };
+
+            hat.state = match source {
+                Some(source) => source.device.hats[index].state,
+                None => hat.hat_type.centered(),
+            };
+        }
+        self.dirty = true;
 
         Ok(())
     }
 }
 
+/// Maximum by key that resolves ties in favor of the earlier item.
+fn first_max_by_key<T, K: Ord>(items: impl Iterator<Item = T>, key: impl Fn(&T) -> K) -> Option<T> {
+    items
+        .fold(None, |max: Option<(K, T)>, item| {
+            let item_key = key(&item);
+            match max {
+                Some((max_key, max_item)) if max_key >= item_key => Some((max_key, max_item)),
+                _ => Some((item_key, item)),
+            }
+        })
+        .map(|(_, item)| item)
+}
+
+/// Builds a [Device] without the driver, e.g. for unit tests and offline tools.
+///
+/// The device has the same shape the driver reports for the same vJoyConf configuration:
+/// buttons released, axes at 0 and hats centered. It is [dirty](Device::is_dirty), since it was never sent.
+///
+/// ```
+/// # use vjoy::{AxisKind, DeviceBuilder, Error, HatType};
+/// let device = DeviceBuilder::new(1)
+///     .buttons(32)
+///     .axes(&[AxisKind::X, AxisKind::Y, AxisKind::Slider])
+///     .hats(1, HatType::Discrete)
+///     .build()?;
+/// assert_eq!(device.num_axes(), 3);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DeviceBuilder {
+    id: u32,
+    num_buttons: u32,
+    axes: Vec<AxisKind>,
+    hats: Vec<HatType>,
+}
+
+impl DeviceBuilder {
+    /// Max. number of buttons per device.
+    pub const MAX_BUTTONS: u32 = 128;
+    /// Max. number of hats per device.
+    pub const MAX_HATS: usize = 4;
+
+    #[profiling::function]
+    pub fn new(device_id: u32) -> Self {
+        Self {
+            id: device_id,
+            ..Default::default()
+        }
+    }
+
+    /// Builder for a device with the given layout.
+    #[profiling::function]
+    pub fn from_capabilities(capabilities: &DeviceCapabilities) -> Self {
+        Self {
+            id: capabilities.device_id,
+            num_buttons: capabilities.num_buttons,
+            axes: capabilities.axes.clone(),
+            hats: capabilities.hats.clone(),
+        }
+    }
+
+    #[profiling::function]
+    pub fn buttons(mut self, num_buttons: u32) -> Self {
+        self.num_buttons = num_buttons;
+        self
+    }
+
+    /// Enables an axis. Axes are ordered by ID regardless of the order they are enabled in.
+    #[profiling::function]
+    pub fn axis(mut self, kind: AxisKind) -> Self {
+        if !self.axes.contains(&kind) {
+            self.axes.push(kind);
+        }
+        self
+    }
+
+    #[profiling::function]
+    pub fn axes(self, kinds: &[AxisKind]) -> Self {
+        kinds.iter().fold(self, |builder, kind| builder.axis(*kind))
+    }
+
+    /// Adds a single hat of the given type after the existing hats.
+    #[profiling::function]
+    pub fn hat(mut self, hat_type: HatType) -> Self {
+        self.hats.push(hat_type);
+        self
+    }
+
+    /// Adds `count` hats of the given type after the existing hats.
+    #[profiling::function]
+    pub fn hats(mut self, count: usize, hat_type: HatType) -> Self {
+        self.hats.extend(std::iter::repeat_n(hat_type, count));
+        self
+    }
+
+    #[profiling::function]
+    pub fn build(&self) -> Result<Device, Error> {
+        let invalid = |reason: String| Error::App(AppError::InvalidDeviceLayout(self.id, reason));
+
+        if !(1..=16).contains(&self.id) {
+            return Err(invalid("device IDs are in the range 1..=16".to_string()));
+        }
+        if self.num_buttons > Self::MAX_BUTTONS {
+            return Err(invalid(format!(
+                "{} buttons exceed the max. of {}",
+                self.num_buttons,
+                Self::MAX_BUTTONS
+            )));
+        }
+        if self.hats.len() > Self::MAX_HATS {
+            return Err(invalid(format!(
+                "{} hats exceed the max. of {}",
+                self.hats.len(),
+                Self::MAX_HATS
+            )));
+        }
+
+        let mut axes = self.axes.clone();
+        axes.sort_unstable();
+
+        Ok(Device {
+            id: self.id,
+            buttons: (1..=self.num_buttons)
+                .map(|button_id| Button {
+                    id: button_id as u8,
+                    state: ButtonState::Released,
+                })
+                .collect(),
+            axes: axes
+                .into_iter()
+                .map(|kind| Axis {
+                    id: kind.id(),
+                    value: 0,
+                    display_name: kind.display_name().to_string(),
+                    hid_usage: kind.hid_usage(),
+                })
+                .collect(),
+            hats: self
+                .hats
+                .iter()
+                .enumerate()
+                .map(|(index, hat_type)| Hat {
+                    id: (index + 1) as u8,
+                    hat_type: *hat_type,
+                    state: hat_type.centered(),
+                })
+                .collect(),
+            dirty: true,
+        })
+    }
+}
+
+/// Changed components of a device with their new states, see [Device::diff].
+///
+/// Components are identified by their 1-based position, as in [Device::set_axis]. For axes, this is not the [AxisKind] ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceDiff {
+    pub device_id: u32,
+    pub buttons: Vec<(u8, ButtonState)>,
+    pub axes: Vec<(u32, i32)>,
+    pub hats: Vec<(u8, HatState)>,
+}
+
+impl DeviceDiff {
+    #[profiling::function]
+    pub fn is_empty(&self) -> bool {
+        self.buttons.is_empty() && self.axes.is_empty() && self.hats.is_empty()
+    }
+
+    /// Number of changed components.
+    #[profiling::function]
+    pub fn len(&self) -> usize {
+        self.buttons.len() + self.axes.len() + self.hats.len()
+    }
+
+    /// Sets all changed components on the device.
+    #[profiling::function]
+    pub fn apply(&self, device: &mut Device) -> Result<(), Error> {
+        for &(button_id, state) in &self.buttons {
+            device.set_button(button_id, state)?;
+        }
+        for &(axis_id, value) in &self.axes {
+            device.set_axis(axis_id, value)?;
+        }
+        for &(hat_id, state) in &self.hats {
+            device.set_hat(hat_id, state)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for DeviceDiff {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Device ID: {} | changed buttons: {} | changed axes: {} | changed hats: {}",
+            self.device_id,
+            self.buttons.len(),
+            self.axes.len(),
+            self.hats.len(),
+        ))
+    }
+}
+
 impl Display for Device {
     #[profiling::function]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "Device ID: {} | button count: {} | axes count: {} | hat count: {} | hat type: {:?}",
+            "Device ID: {} | button count: {} | axes count: {} | hat count: {} | hat type: {}",
             self.id,
             self.buttons.len(),
             self.axes.len(),
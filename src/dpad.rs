@@ -0,0 +1,256 @@
+use crate::button::ButtonState;
+use crate::hat::{FourWayHat, Hat, HatAngle, HatState, HatType};
+use std::fmt::Display;
+
+/// Resolution policy for simultaneous opposite directions (SOCD), e.g. left and right pressed at the same time.
+///
+/// - `Neutral`: opposite directions cancel each other out.
+/// - `LastInputWins`: the most recently pressed direction wins.
+/// - `FirstInputWins`: the direction that was pressed first wins.
+/// - `UpPriority`: up wins over down, left and right cancel each other out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum SocdPolicy {
+    #[default]
+    Neutral,
+    LastInputWins,
+    FirstInputWins,
+    UpPriority,
+}
+
+impl Display for SocdPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            SocdPolicy::Neutral => "neutral",
+            SocdPolicy::LastInputWins => "last input wins",
+            SocdPolicy::FirstInputWins => "first input wins",
+            SocdPolicy::UpPriority => "up priority",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+/// States of the four directional buttons of a D-pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DPadButtons {
+    pub up: ButtonState,
+    pub down: ButtonState,
+    pub left: ButtonState,
+    pub right: ButtonState,
+}
+
+impl DPadButtons {
+    /// Directional buttons that correspond to a hat state.
+    ///
+    /// Continuous hats are split into eight 45° sectors, so diagonals press two adjacent buttons.
+    #[profiling::function]
+    pub fn from_hat_state(state: HatState) -> Self {
+        let pressed = |condition: bool| {
+            if condition {
+                ButtonState::Pressed
+            } else {
+                ButtonState::Released
+            }
+        };
+
+        let Some(angle) = state.angle() else {
+            return Self::default();
+        };
+
+        // Sector 0 is North, counting clockwise in steps of 45°
+        let sector = ((angle.hundredths() + 2250) / 4500) % 8;
+        Self {
+            up: pressed(matches!(sector, 7 | 0 | 1)),
+            right: pressed(matches!(sector, 1..=3)),
+            down: pressed(matches!(sector, 3..=5)),
+            left: pressed(matches!(sector, 5..=7)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Direction {
+    state: ButtonState,
+    pressed_at: u64,
+}
+
+/// Converts four directional buttons into a hat state, resolving simultaneous opposite directions via a [SocdPolicy].
+///
+/// The converter tracks the order in which directions were pressed, so it has to be fed every change via [update](Self::update).
+/// When a vertical and a horizontal direction are active at once, continuous hats report the diagonal,
+/// while discrete hats report the more recently pressed direction.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, ButtonState, DPad, DPadButtons, Error, SocdPolicy};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// let mut dpad = DPad::new(SocdPolicy::LastInputWins);
+/// dpad.update(DPadButtons {
+///     left: ButtonState::Pressed,
+///     ..Default::default()
+/// });
+///
+/// let device_1 = vjoy.get_device_state_mut(1)?;
+/// if let Some(hat) = device_1.hats_mut().next() {
+///     dpad.apply(hat);
+/// }
+/// vjoy.update_all_devices()?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DPad {
+    policy: SocdPolicy,
+    up: Direction,
+    down: Direction,
+    left: Direction,
+    right: Direction,
+    counter: u64,
+}
+
+impl DPad {
+    #[profiling::function]
+    pub fn new(policy: SocdPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    #[profiling::function]
+    pub fn policy(&self) -> SocdPolicy {
+        self.policy
+    }
+
+    #[profiling::function]
+    pub fn set_policy(&mut self, policy: SocdPolicy) {
+        self.policy = policy;
+    }
+
+    /// Raw states of the directional buttons as last passed to [update](Self::update).
+    #[profiling::function]
+    pub fn buttons(&self) -> DPadButtons {
+        DPadButtons {
+            up: self.up.state,
+            down: self.down.state,
+            left: self.left.state,
+            right: self.right.state,
+        }
+    }
+
+    #[profiling::function]
+    pub fn update(&mut self, buttons: DPadButtons) {
+        let counter = &mut self.counter;
+        let mut track = |direction: &mut Direction, state: ButtonState| {
+            if direction.state == ButtonState::Released && state == ButtonState::Pressed {
+                *counter += 1;
+                direction.pressed_at = *counter;
+            }
+            direction.state = state;
+        };
+
+        track(&mut self.up, buttons.up);
+        track(&mut self.down, buttons.down);
+        track(&mut self.left, buttons.left);
+        track(&mut self.right, buttons.right);
+    }
+
+    /// Directional buttons after SOCD resolution. At most one button per axis is pressed.
+    #[profiling::function]
+    pub fn resolved(&self) -> DPadButtons {
+        let (up, down) = self.resolve(self.up, self.down, true);
+        let (left, right) = self.resolve(self.left, self.right, false);
+
+        DPadButtons {
+            up,
+            down,
+            left,
+            right,
+        }
+    }
+
+    /// Resolved direction including diagonals or `None` if centered.
+    #[profiling::function]
+    pub fn angle(&self) -> Option<HatAngle> {
+        let resolved = self.resolved();
+        let axis = |positive: ButtonState, negative: ButtonState| -> f32 {
+            match (positive, negative) {
+                (ButtonState::Pressed, _) => 1.0,
+                (_, ButtonState::Pressed) => -1.0,
+                _ => 0.0,
+            }
+        };
+
+        HatAngle::from_vector(
+            axis(resolved.right, resolved.left),
+            axis(resolved.up, resolved.down),
+        )
+    }
+
+    /// Resolved direction for a 4-way hat. Diagonals resolve to the more recently pressed direction.
+    #[profiling::function]
+    pub fn four_way(&self) -> FourWayHat {
+        let resolved = self.resolved();
+
+        let vertical = match (resolved.up, resolved.down) {
+            (ButtonState::Pressed, _) => Some((FourWayHat::North, self.up.pressed_at)),
+            (_, ButtonState::Pressed) => Some((FourWayHat::South, self.down.pressed_at)),
+            _ => None,
+        };
+        let horizontal = match (resolved.right, resolved.left) {
+            (ButtonState::Pressed, _) => Some((FourWayHat::East, self.right.pressed_at)),
+            (_, ButtonState::Pressed) => Some((FourWayHat::West, self.left.pressed_at)),
+            _ => None,
+        };
+
+        match (vertical, horizontal) {
+            (Some((v, v_at)), Some((h, h_at))) => {
+                if h_at > v_at {
+                    h
+                } else {
+                    v
+                }
+            }
+            (Some((v, _)), None) => v,
+            (None, Some((h, _))) => h,
+            (None, None) => FourWayHat::Centered,
+        }
+    }
+
+    /// Writes the resolved direction to a hat, using [four_way](Self::four_way) for discrete and [angle](Self::angle) for continuous hats.
+    #[profiling::function]
+    pub fn apply(&self, hat: &mut Hat) {
+        let state = match hat.hat_type() {
+            HatType::Discrete => HatState::Discrete(self.four_way()),
+            HatType::Continuous => match self.angle() {
+                Some(angle) => HatState::from(angle),
+                None => HatType::Continuous.centered(),
+            },
+        };
+        hat.set(state);
+    }
+
+    fn resolve(
+        &self,
+        positive: Direction,
+        negative: Direction,
+        vertical: bool,
+    ) -> (ButtonState, ButtonState) {
+        use ButtonState::{Pressed, Released};
+
+        if positive.state != Pressed || negative.state != Pressed {
+            return (positive.state, negative.state);
+        }
+
+        match self.policy {
+            SocdPolicy::Neutral => (Released, Released),
+            SocdPolicy::UpPriority if vertical => (Pressed, Released),
+            SocdPolicy::UpPriority => (Released, Released),
+            SocdPolicy::LastInputWins if positive.pressed_at > negative.pressed_at => {
+                (Pressed, Released)
+            }
+            SocdPolicy::LastInputWins => (Released, Pressed),
+            SocdPolicy::FirstInputWins if positive.pressed_at < negative.pressed_at => {
+                (Pressed, Released)
+            }
+            SocdPolicy::FirstInputWins => (Released, Pressed),
+        }
+    }
+}
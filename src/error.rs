@@ -1,4 +1,6 @@
+use crate::hat::{HatState, HatType};
 use thiserror::Error;
+#[cfg(target_os = "windows")]
 use vjoy_sys::VjdStat;
 
 #[derive(Error, Debug)]
@@ -6,19 +8,35 @@ pub enum Error {
     #[error("app error: {0}.")]
     App(AppError),
 
+    #[cfg(target_os = "windows")]
     #[error("ffi error: {0}.")]
     Ffi(FFIError),
+
+    #[error("ffb error: {0}.")]
+    Ffb(FfbError),
+
+    #[error("io error: {0}.")]
+    Io(std::io::Error),
 }
 
 #[derive(Error, Debug)]
 /// Errors that arise from incorrect usage of the wrapper API - e.g. requesting buttons outside reported limits.
 pub enum AppError {
-    #[error("multiple devices with configuration buttons: {0} axes: {0} hats: {0} found.")]
+    #[error("multiple devices with configuration buttons: {0} axes: {1} hats: {2} found.")]
     DeviceConfigMultipleFound(u32, u32, u32),
 
-    #[error("device with configuration buttons: {0} axes: {0} hats: {0} was not found.")]
+    #[error("device with configuration buttons: {0} axes: {1} hats: {2} was not found.")]
     DeviceConfigNotFound(u32, u32, u32),
 
+    #[error("no device matches the query {0}.")]
+    NoDeviceMatches(String),
+
+    #[error("device alias \"{0}\" is not registered.")]
+    AliasNotFound(String),
+
+    #[error("no device matches the alias \"{0}\" with query {1}.")]
+    AliasNotMatched(String, String),
+
     #[error("device with ID {0} was not found.")]
     DeviceNotFound(u32),
 
@@ -30,8 +48,33 @@ pub enum AppError {
 
     #[error("hat {1} of Device {0} could not be found.")]
     HatNotFound(u32, u8),
+
+    #[error("hat {1} of Device {0} is a {2} hat and cannot be set to {3}.")]
+    HatTypeMismatch(u32, u8, HatType, HatState),
+
+    #[error("hat angle {0} is outside of the valid range 0..360°.")]
+    InvalidHatAngle(f32),
+
+    #[error("snapshot of Device {0} does not match the configuration of the device.")]
+    SnapshotMismatch(u32),
+
+    #[error("Device {0} does not match the required capabilities: {1}.")]
+    CapabilityMismatch(u32, String),
+
+    #[error("Device {0} cannot be built: {1}.")]
+    InvalidDeviceLayout(u32, String),
+
+    #[error("invalid line {0} in recording: {1}.")]
+    InvalidRecordingLine(usize, String),
+
+    #[error("invalid script line {0}: {1}.")]
+    InvalidScript(usize, String),
+
+    #[error("invalid row {0} in timeline: {1}.")]
+    InvalidTimelineRow(usize, String),
 }
 
+#[cfg(target_os = "windows")]
 #[derive(Error, Debug)]
 /// Errors that arise from incorrect usage of the C API - e.g. updating devices before acquisition.
 pub enum FFIError {
@@ -44,6 +87,15 @@ pub enum FFIError {
     #[error("device with ID {0} could not be updated from pointer data. Device Status: {1}")]
     DeviceDataCouldNotBeUpdated(u32, VjdStat),
 
+    #[error("device with ID {0} could not be reset. Device Status: {1}")]
+    DeviceCouldNotBeReset(u32, VjdStat),
+
+    #[error("buttons of Device {0} could not be reset. Device Status: {1}")]
+    ButtonsCouldNotBeReset(u32, VjdStat),
+
+    #[error("hats of Device {0} could not be reset. Device Status: {1}")]
+    HatsCouldNotBeReset(u32, VjdStat),
+
     #[error("button {1} of Device {0} could not be set. Device Status: {2}")]
     ButtonCouldNotBeSet(u32, u8, VjdStat),
 
@@ -53,3 +105,25 @@ pub enum FFIError {
     #[error("axis {1} of Device {0} could not be set. Device Status: {2}")]
     AxisCouldNotBeSet(u32, u32, VjdStat),
 }
+
+#[derive(Error, Debug)]
+/// Errors that arise from malformed or unsupported force feedback packets.
+pub enum FfbError {
+    #[error("unknown FFB command {0:#x}.")]
+    UnknownCommand(u32),
+
+    #[error("FFB packet for invalid device ID {0}.")]
+    InvalidDeviceId(u32),
+
+    #[error("FFB report {0:#x} is too short: expected {1} bytes, got {2}.")]
+    PacketTooShort(u8, usize, usize),
+
+    #[error("FFB report {0:#x} is not supported.")]
+    UnsupportedReport(u8),
+
+    #[error("invalid {0} {1} in FFB report.")]
+    InvalidValue(&'static str, u32),
+
+    #[error("invalid line {0} in FFB session: {1}.")]
+    InvalidSessionLine(usize, String),
+}
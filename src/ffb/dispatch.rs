@@ -0,0 +1,176 @@
+use crate::ffb::{FfbPacket, FfbReport, RawFfbPacket};
+use log::{error, trace, warn};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+#[cfg(target_os = "windows")]
+use std::sync::Mutex;
+use std::sync::mpsc::{Receiver, Sender, channel};
+#[cfg(target_os = "windows")]
+use vjoy_sys::PVOID;
+
+type FfbCallback = Box<dyn FnMut(u32, &FfbPacket) + Send + 'static>;
+type FfbRawCallback = Box<dyn FnMut(&RawFfbPacket) + Send + 'static>;
+
+/// Routes raw FFB packets to registered callbacks and channels.
+///
+/// [VJoy](crate::VJoy) feeds one dispatcher from the driver's FFB thread, see [VJoy::on_ffb](crate::VJoy::on_ffb) and [VJoy::ffb_receiver](crate::VJoy::ffb_receiver).
+/// A dispatcher can also be fed directly, e.g. with the packets of an [FfbSession](crate::ffb::FfbSession).
+///
+/// Raw callbacks receive every packet. Parsed callbacks and channels only receive packets for the owned devices that parse successfully,
+/// all other packets are dropped with a log message.
+#[derive(Default)]
+pub struct FfbDispatcher {
+    device_ids: Vec<u32>,
+    callbacks: Vec<FfbCallback>,
+    raw_callbacks: Vec<FfbRawCallback>,
+    senders: Vec<Sender<FfbReport>>,
+}
+
+impl std::fmt::Debug for FfbDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FfbDispatcher")
+            .field("device_ids", &self.device_ids)
+            .field("callbacks", &self.callbacks.len())
+            .field("raw_callbacks", &self.raw_callbacks.len())
+            .field("senders", &self.senders.len())
+            .finish()
+    }
+}
+
+impl FfbDispatcher {
+    /// Dispatcher for packets sent to the given devices.
+    #[profiling::function]
+    pub fn new<I: IntoIterator<Item = u32>>(device_ids: I) -> Self {
+        Self {
+            device_ids: device_ids.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[profiling::function]
+    pub fn device_ids(&self) -> &[u32] {
+        &self.device_ids
+    }
+
+    /// Registers a callback for parsed packets. Panics inside the callback are caught and logged.
+    #[profiling::function]
+    pub fn on_packet<F>(&mut self, callback: F)
+    where
+        F: FnMut(u32, &FfbPacket) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Registers a callback for every packet before it is parsed. Panics inside the callback are caught and logged.
+    #[profiling::function]
+    pub fn on_raw_packet<F>(&mut self, callback: F)
+    where
+        F: FnMut(&RawFfbPacket) + Send + 'static,
+    {
+        self.raw_callbacks.push(Box::new(callback));
+    }
+
+    /// Returns a channel that receives every parsed packet. Dropped receivers are unregistered on the next packet.
+    #[profiling::function]
+    pub fn receiver(&mut self) -> Receiver<FfbReport> {
+        let (sender, receiver) = channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Number of registered channels, including dropped receivers that were not unregistered yet.
+    #[profiling::function]
+    pub fn num_receivers(&self) -> usize {
+        self.senders.len()
+    }
+
+    #[profiling::function]
+    pub fn dispatch(&mut self, raw: &RawFfbPacket) {
+        for callback in &mut self.raw_callbacks {
+            let result = catch_unwind(AssertUnwindSafe(|| callback(raw)));
+            if result.is_err() {
+                error!("Raw FFB callback panicked");
+            }
+        }
+
+        let device_id = match raw.device_id() {
+            Ok(device_id) => device_id,
+            Err(e) => {
+                warn!("Dropped FFB packet: {}", e);
+                return;
+            }
+        };
+
+        if !self.device_ids.contains(&device_id) {
+            trace!(
+                "Dropped FFB packet for device {} not owned by this feeder",
+                device_id
+            );
+            return;
+        }
+
+        let packet = match raw.parse() {
+            Ok(packet) => packet,
+            Err(e) => {
+                warn!("Dropped FFB packet for device {}: {}", device_id, e);
+                return;
+            }
+        };
+
+        for callback in &mut self.callbacks {
+            let result = catch_unwind(AssertUnwindSafe(|| callback(device_id, &packet)));
+            if result.is_err() {
+                error!(
+                    "FFB callback panicked while handling packet for device {}",
+                    device_id
+                );
+            }
+        }
+
+        // Receivers that were dropped are unregistered on their first failed send
+        self.senders
+            .retain(|sender| sender.send(FfbReport { device_id, packet }).is_ok());
+    }
+}
+
+/// Mirror of `_FFB_DATA` from vjoyinterface.h, which vjoy-sys doesn't generate bindings for.
+#[cfg(target_os = "windows")]
+#[repr(C)]
+struct FfbData {
+    size: u32,
+    cmd: u32,
+    data: *const u8,
+}
+
+/// Entry point registered via `FfbRegisterGenCB`. Called by the driver on its own thread with the `CALLBACK` (stdcall) convention.
+///
+/// `data` points to an `FFB_DATA` whose `size` includes the 8 header bytes,
+/// `user_data` to the leaked `Mutex<FfbDispatcher>` of [VJoy](crate::VJoy), which stays valid for the rest of the process.
+#[cfg(target_os = "windows")]
+pub(crate) unsafe extern "system" fn ffb_trampoline(data: PVOID, user_data: PVOID) {
+    // Unwinding into the driver is undefined behavior
+    let result = catch_unwind(|| {
+        if data.is_null() || user_data.is_null() {
+            return;
+        }
+
+        let raw = unsafe {
+            let ffb_data = &*(data as *const FfbData);
+            let len = (ffb_data.size as usize).saturating_sub(8);
+            if ffb_data.data.is_null() || len == 0 {
+                return;
+            }
+            RawFfbPacket::new(
+                ffb_data.cmd,
+                std::slice::from_raw_parts(ffb_data.data, len).to_vec(),
+            )
+        };
+
+        let dispatcher = unsafe { &*(user_data as *const Mutex<FfbDispatcher>) };
+        let mut dispatcher = dispatcher.lock().unwrap_or_else(|e| e.into_inner());
+        dispatcher.dispatch(&raw);
+    });
+
+    if result.is_err() {
+        error!("FFB dispatch panicked");
+    }
+}
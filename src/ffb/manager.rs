@@ -0,0 +1,416 @@
+use crate::ffb::{
+    ConditionReport, ConstantReport, DeviceControl, EffectOperation, EffectReport, EffectType,
+    EnvelopeReport, FfbPacket, FfbReport, PeriodicReport, RampReport,
+};
+use log::trace;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Highest effect block index the driver hands out. Block indices start at 1.
+pub const MAX_EFFECT_BLOCK_INDEX: u8 = 100;
+
+/// Loop count that repeats an effect until it is stopped.
+pub const INFINITE_LOOP_COUNT: u8 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlockLoadStatus {
+    Success,
+    Full,
+    Error,
+}
+
+/// Answer to a create new effect request: the allocated block index (0 if none) and the load status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockLoad {
+    pub block_index: u8,
+    pub status: BlockLoadStatus,
+}
+
+/// Playback state of an effect. `started_at` is the timestamp of the start operation, including any pause time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EffectState {
+    #[default]
+    Stopped,
+    Playing {
+        started_at: Duration,
+        loop_count: u8,
+    },
+}
+
+/// An effect created on a device, with the latest parameters the game sent for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Effect {
+    pub(crate) block_index: u8,
+    pub(crate) effect_type: EffectType,
+    pub(crate) report: Option<EffectReport>,
+    pub(crate) envelope: Option<EnvelopeReport>,
+    pub(crate) conditions: [Option<ConditionReport>; 2],
+    pub(crate) periodic: Option<PeriodicReport>,
+    pub(crate) constant: Option<ConstantReport>,
+    pub(crate) ramp: Option<RampReport>,
+    pub(crate) state: EffectState,
+}
+
+impl Effect {
+    #[profiling::function]
+    fn new(block_index: u8, effect_type: EffectType) -> Self {
+        Self {
+            block_index,
+            effect_type,
+            report: None,
+            envelope: None,
+            conditions: [None; 2],
+            periodic: None,
+            constant: None,
+            ramp: None,
+            state: EffectState::Stopped,
+        }
+    }
+
+    #[profiling::function]
+    pub fn block_index(&self) -> u8 {
+        self.block_index
+    }
+
+    #[profiling::function]
+    pub fn effect_type(&self) -> EffectType {
+        self.effect_type
+    }
+
+    #[profiling::function]
+    pub fn report(&self) -> Option<&EffectReport> {
+        self.report.as_ref()
+    }
+
+    #[profiling::function]
+    pub fn envelope(&self) -> Option<&EnvelopeReport> {
+        self.envelope.as_ref()
+    }
+
+    /// Condition parameters for the X (index 0) and Y (index 1) axis.
+    #[profiling::function]
+    pub fn conditions(&self) -> &[Option<ConditionReport>; 2] {
+        &self.conditions
+    }
+
+    #[profiling::function]
+    pub fn periodic(&self) -> Option<&PeriodicReport> {
+        self.periodic.as_ref()
+    }
+
+    #[profiling::function]
+    pub fn constant(&self) -> Option<&ConstantReport> {
+        self.constant.as_ref()
+    }
+
+    #[profiling::function]
+    pub fn ramp(&self) -> Option<&RampReport> {
+        self.ramp.as_ref()
+    }
+
+    #[profiling::function]
+    pub fn state(&self) -> EffectState {
+        self.state
+    }
+
+    /// Time into the current loop of the effect at `now`, or `None` if it is stopped, still in its start delay, or finished.
+    #[profiling::function]
+    pub fn playback_time(&self, now: Duration) -> Option<Duration> {
+        let EffectState::Playing {
+            started_at,
+            loop_count,
+        } = self.state
+        else {
+            return None;
+        };
+
+        let start_delay = self.report.map(|r| r.start_delay()).unwrap_or_default();
+        let elapsed = now.checked_sub(started_at + start_delay)?;
+
+        let Some(duration) = self.report.and_then(|r| r.duration()) else {
+            return Some(elapsed);
+        };
+        if duration.is_zero() {
+            return Some(elapsed);
+        }
+
+        let loop_index = elapsed.as_nanos() / duration.as_nanos();
+        if loop_count != INFINITE_LOOP_COUNT && loop_index >= loop_count.max(1) as u128 {
+            return None;
+        }
+
+        Some(Duration::from_nanos(
+            (elapsed.as_nanos() % duration.as_nanos()) as u64,
+        ))
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Effect block {} | type: {} | state: {:?}",
+            self.block_index, self.effect_type, self.state
+        ))
+    }
+}
+
+/// Model of the PID (physical interface device) state of one vJoy device: which effects exist, their parameters and which are running.
+///
+/// Feed every FFB packet for the device via [apply](Self::apply) together with the time it was received.
+/// Timestamps are durations since an arbitrary epoch, so the manager is deterministic and can be driven from recorded sessions.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, Error};
+/// # use vjoy::ffb::FfbEffectManager;
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// let receiver = vjoy.ffb_receiver();
+/// let mut manager = FfbEffectManager::new(1);
+/// let start = std::time::Instant::now();
+///
+/// for report in receiver.iter() {
+///     manager.apply_report(&report, start.elapsed());
+///     for (effect, time) in manager.active_effects(start.elapsed()) {
+///         println!("{effect} at {time:?}");
+///     }
+/// }
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FfbEffectManager {
+    device_id: u32,
+    effects: BTreeMap<u8, Effect>,
+    gain: u8,
+    actuators_enabled: bool,
+    paused_at: Option<Duration>,
+}
+
+impl FfbEffectManager {
+    #[profiling::function]
+    pub fn new(device_id: u32) -> Self {
+        Self {
+            device_id,
+            effects: BTreeMap::new(),
+            gain: u8::MAX,
+            actuators_enabled: true,
+            paused_at: None,
+        }
+    }
+
+    #[profiling::function]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    /// Global device gain in the range 0..=255.
+    #[profiling::function]
+    pub fn gain(&self) -> u8 {
+        self.gain
+    }
+
+    #[profiling::function]
+    pub fn actuators_enabled(&self) -> bool {
+        self.actuators_enabled
+    }
+
+    #[profiling::function]
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    #[profiling::function]
+    pub fn effect(&self, block_index: u8) -> Option<&Effect> {
+        self.effects.get(&block_index)
+    }
+
+    /// All created effects, ordered by block index.
+    #[profiling::function]
+    pub fn effects(&self) -> impl Iterator<Item = &Effect> {
+        self.effects.values()
+    }
+
+    /// Effects that are currently producing force, with their time into the current loop.
+    ///
+    /// While the device is paused, effects are frozen at the time of the pause. Disabled actuators yield no effects.
+    #[profiling::function]
+    pub fn active_effects(&self, now: Duration) -> Vec<(&Effect, Duration)> {
+        if !self.actuators_enabled {
+            return Vec::new();
+        }
+
+        let now = self.paused_at.unwrap_or(now);
+        self.effects
+            .values()
+            .filter_map(|effect| effect.playback_time(now).map(|time| (effect, time)))
+            .collect()
+    }
+
+    /// Lowest free block index or `None` if all block indices are in use.
+    #[profiling::function]
+    pub fn next_free_index(&self) -> Option<u8> {
+        (1..=MAX_EFFECT_BLOCK_INDEX).find(|index| !self.effects.contains_key(index))
+    }
+
+    /// Allocates the next free block index for a new effect.
+    #[profiling::function]
+    pub fn create_effect(&mut self, effect_type: EffectType) -> BlockLoad {
+        let Some(block_index) = self.next_free_index() else {
+            return BlockLoad {
+                block_index: 0,
+                status: BlockLoadStatus::Full,
+            };
+        };
+
+        self.effects
+            .insert(block_index, Effect::new(block_index, effect_type));
+        trace!(
+            "Device {} created {} effect at block {}",
+            self.device_id, effect_type, block_index
+        );
+
+        BlockLoad {
+            block_index,
+            status: BlockLoadStatus::Success,
+        }
+    }
+
+    /// Applies a report if it is addressed to this device. See [apply](Self::apply).
+    #[profiling::function]
+    pub fn apply_report(&mut self, report: &FfbReport, now: Duration) -> Option<BlockLoad> {
+        if report.device_id != self.device_id {
+            return None;
+        }
+
+        self.apply(&report.packet, now)
+    }
+
+    /// Applies a packet received at `now` to the effect state.
+    ///
+    /// Returns the block load answer for [FfbPacket::CreateNewEffect] requests, `None` for all other packets.
+    /// Parameter reports for unknown block indices are ignored, except for set effect reports, which create the effect.
+    #[profiling::function]
+    pub fn apply(&mut self, packet: &FfbPacket, now: Duration) -> Option<BlockLoad> {
+        match *packet {
+            FfbPacket::CreateNewEffect(effect_type) => {
+                return Some(self.create_effect(effect_type));
+            }
+            FfbPacket::Effect(report) => {
+                let effect = self
+                    .effects
+                    .entry(report.block_index)
+                    .or_insert_with(|| Effect::new(report.block_index, report.effect_type));
+                effect.effect_type = report.effect_type;
+                effect.report = Some(report);
+            }
+            FfbPacket::Envelope(report) => {
+                if let Some(effect) = self.effect_mut(report.block_index) {
+                    effect.envelope = Some(report);
+                }
+            }
+            FfbPacket::Condition(report) => {
+                if let Some(effect) = self.effect_mut(report.block_index) {
+                    effect.conditions[report.is_y as usize] = Some(report);
+                }
+            }
+            FfbPacket::Periodic(report) => {
+                if let Some(effect) = self.effect_mut(report.block_index) {
+                    effect.periodic = Some(report);
+                }
+            }
+            FfbPacket::Constant(report) => {
+                if let Some(effect) = self.effect_mut(report.block_index) {
+                    effect.constant = Some(report);
+                }
+            }
+            FfbPacket::Ramp(report) => {
+                if let Some(effect) = self.effect_mut(report.block_index) {
+                    effect.ramp = Some(report);
+                }
+            }
+            FfbPacket::EffectOperation(report) => {
+                let started_at = self.paused_at.unwrap_or(now);
+                let playing = EffectState::Playing {
+                    started_at,
+                    loop_count: report.loop_count,
+                };
+
+                match report.operation {
+                    EffectOperation::Start => {
+                        if let Some(effect) = self.effect_mut(report.block_index) {
+                            effect.state = playing;
+                        }
+                    }
+                    EffectOperation::Solo => {
+                        for effect in self.effects.values_mut() {
+                            effect.state = if effect.block_index == report.block_index {
+                                playing
+                            } else {
+                                EffectState::Stopped
+                            };
+                        }
+                    }
+                    EffectOperation::Stop => {
+                        if let Some(effect) = self.effect_mut(report.block_index) {
+                            effect.state = EffectState::Stopped;
+                        }
+                    }
+                }
+            }
+            FfbPacket::BlockFree(block_index) => {
+                self.effects.remove(&block_index);
+            }
+            FfbPacket::DeviceControl(control) => self.device_control(control, now),
+            FfbPacket::DeviceGain(gain) => self.gain = gain,
+        }
+
+        None
+    }
+
+    #[profiling::function]
+    fn device_control(&mut self, control: DeviceControl, now: Duration) {
+        match control {
+            DeviceControl::EnableActuators => self.actuators_enabled = true,
+            DeviceControl::DisableActuators => self.actuators_enabled = false,
+            DeviceControl::StopAllEffects => {
+                for effect in self.effects.values_mut() {
+                    effect.state = EffectState::Stopped;
+                }
+            }
+            DeviceControl::Reset => {
+                self.effects.clear();
+                self.actuators_enabled = true;
+                self.paused_at = None;
+            }
+            DeviceControl::Pause => {
+                if self.paused_at.is_none() {
+                    self.paused_at = Some(now);
+                }
+            }
+            DeviceControl::Continue => {
+                let Some(paused_at) = self.paused_at.take() else {
+                    return;
+                };
+
+                // Shift start times so effects resume from the time step they were paused at
+                let pause = now.saturating_sub(paused_at);
+                for effect in self.effects.values_mut() {
+                    if let EffectState::Playing { started_at, .. } = &mut effect.state {
+                        *started_at += pause;
+                    }
+                }
+            }
+        }
+    }
+
+    fn effect_mut(&mut self, block_index: u8) -> Option<&mut Effect> {
+        let effect = self.effects.get_mut(&block_index);
+        if effect.is_none() {
+            trace!(
+                "Device {} ignored FFB packet for unknown block {}",
+                self.device_id, block_index
+            );
+        }
+
+        effect
+    }
+}
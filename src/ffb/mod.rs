@@ -0,0 +1,31 @@
+//! Force feedback (FFB) support.
+//!
+//! The vJoy driver forwards the FFB output and feature reports a game sends to a device as raw `FFB_DATA` buffers.
+//! [RawFfbPacket] holds such a buffer and [parses](RawFfbPacket::parse) it into a typed [FfbPacket].
+//!
+//! Parsing is pure Rust over bytes and does not depend on the driver, so captured packets can be parsed on any platform.
+//!
+//! [FfbEffectManager] tracks the effects of one device from the stream of packets and [FfbSynthesizer] turns its active effects into forces per axis.
+//! For gamepads without force feedback, [RumbleMixer] downmixes them into dual-motor rumble.
+//! [FfbSession] captures raw packets to a file and replays them offline.
+//!
+//! To receive packets from the driver, register a callback via [VJoy::on_ffb](crate::VJoy::on_ffb) or a channel via [VJoy::ffb_receiver](crate::VJoy::ffb_receiver).
+//! Both are backed by an [FfbDispatcher], which routes packets to callbacks and channels without the driver as well.
+
+mod packet;
+pub use packet::*;
+
+mod manager;
+pub use manager::*;
+
+mod synth;
+pub use synth::*;
+
+mod session;
+pub use session::*;
+
+mod rumble;
+pub use rumble::*;
+
+pub(crate) mod dispatch;
+pub use dispatch::FfbDispatcher;
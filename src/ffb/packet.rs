@@ -0,0 +1,554 @@
+use crate::error::{Error, FfbError};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// `FFB_DATA::cmd` of HID output reports - all effect parameter, operation and device control reports.
+pub const IOCTL_HID_WRITE_REPORT: u32 = 0x000B_000F;
+
+/// `FFB_DATA::cmd` of HID feature reports - e.g. the create new effect report.
+pub const IOCTL_HID_SET_FEATURE: u32 = 0x000B_0191;
+
+/// Offset added to the report ID of feature reports to distinguish them from output reports with the same ID.
+const FEATURE_REPORT_OFFSET: u8 = 0x10;
+
+/// Raw FFB packet as delivered by the driver in `FFB_DATA`.
+///
+/// `data[0]` holds the device ID in the high nibble and the HID report ID in the low nibble, followed by the report payload.
+/// All multi-byte fields of the payload are little-endian:
+///
+/// | Report | Payload layout |
+/// |---|---|
+/// | Set effect (0x01) | block index: u8, effect type: u8, duration: u16, trigger repeat: u16, sample period: u16, start delay: u16, gain: u8, trigger button: u8, axes/direction enable: u8, direction X: u16, direction Y: u16 |
+/// | Set envelope (0x02) | block index: u8, attack level: u16, fade level: u16, attack time: u32, fade time: u32 |
+/// | Set condition (0x03) | block index: u8, parameter block offset: u8, center point offset: i16, positive coefficient: i16, negative coefficient: i16, positive saturation: u16, negative saturation: u16, dead band: u16 |
+/// | Set periodic (0x04) | block index: u8, magnitude: u16, offset: i16, phase: u16, period: u32 |
+/// | Set constant force (0x05) | block index: u8, magnitude: i16 |
+/// | Set ramp force (0x06) | block index: u8, start: i16, end: i16 |
+/// | Effect operation (0x0A) | block index: u8, operation: u8, loop count: u8 |
+/// | Block free (0x0B) | block index: u8 |
+/// | Device control (0x0C) | control: u8 |
+/// | Device gain (0x0D) | gain: u8 |
+/// | Create new effect (feature 0x01) | effect type: u8 |
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RawFfbPacket {
+    pub cmd: u32,
+    pub data: Vec<u8>,
+}
+
+impl RawFfbPacket {
+    #[profiling::function]
+    pub fn new(cmd: u32, data: Vec<u8>) -> Self {
+        Self { cmd, data }
+    }
+
+    /// ID of the vJoy device the packet is addressed to.
+    #[profiling::function]
+    pub fn device_id(&self) -> Result<u32, Error> {
+        let Some(first) = self.data.first() else {
+            return Err(Error::Ffb(FfbError::PacketTooShort(0, 1, 0)));
+        };
+
+        let device_id = (first >> 4) as u32;
+        if device_id == 0 {
+            return Err(Error::Ffb(FfbError::InvalidDeviceId(device_id)));
+        }
+
+        Ok(device_id)
+    }
+
+    /// HID report ID of the packet. Feature reports are offset by 0x10, e.g. create new effect is 0x11.
+    #[profiling::function]
+    pub fn report_id(&self) -> Result<u8, Error> {
+        let Some(first) = self.data.first() else {
+            return Err(Error::Ffb(FfbError::PacketTooShort(0, 1, 0)));
+        };
+
+        let report_id = first & 0x0F;
+        match self.cmd {
+            IOCTL_HID_WRITE_REPORT => Ok(report_id),
+            IOCTL_HID_SET_FEATURE => Ok(report_id + FEATURE_REPORT_OFFSET),
+            cmd => Err(Error::Ffb(FfbError::UnknownCommand(cmd))),
+        }
+    }
+
+    /// Parses the packet together with its device ID.
+    #[profiling::function]
+    pub fn report(&self) -> Result<FfbReport, Error> {
+        Ok(FfbReport {
+            device_id: self.device_id()?,
+            packet: self.parse()?,
+        })
+    }
+
+    #[profiling::function]
+    pub fn parse(&self) -> Result<FfbPacket, Error> {
+        let report_id = self.report_id()?;
+        let reader = Reader {
+            report_id,
+            payload: &self.data[1..],
+        };
+
+        let packet = match report_id {
+            0x01 => FfbPacket::Effect(EffectReport {
+                block_index: reader.u8(0)?,
+                effect_type: EffectType::try_from(reader.u8(1)?)?,
+                duration: reader.u16(2)?,
+                trigger_repeat: reader.u16(4)?,
+                sample_period: reader.u16(6)?,
+                start_delay: reader.u16(8)?,
+                gain: reader.u8(10)?,
+                trigger_button: reader.u8(11)?,
+                axes_enabled: reader.u8(12)? & 0x03,
+                polar: reader.u8(12)? & 0x04 != 0,
+                direction_x: reader.u16(13)?,
+                direction_y: reader.u16(15)?,
+            }),
+            0x02 => FfbPacket::Envelope(EnvelopeReport {
+                block_index: reader.u8(0)?,
+                attack_level: reader.u16(1)?,
+                fade_level: reader.u16(3)?,
+                attack_time: reader.u32(5)?,
+                fade_time: reader.u32(9)?,
+            }),
+            0x03 => FfbPacket::Condition(ConditionReport {
+                block_index: reader.u8(0)?,
+                is_y: reader.u8(1)? & 0x01 != 0,
+                center_point_offset: reader.i16(2)?,
+                positive_coefficient: reader.i16(4)?,
+                negative_coefficient: reader.i16(6)?,
+                positive_saturation: reader.u16(8)?,
+                negative_saturation: reader.u16(10)?,
+                dead_band: reader.u16(12)?,
+            }),
+            0x04 => FfbPacket::Periodic(PeriodicReport {
+                block_index: reader.u8(0)?,
+                magnitude: reader.u16(1)?,
+                offset: reader.i16(3)?,
+                phase: reader.u16(5)?,
+                period: reader.u32(7)?,
+            }),
+            0x05 => FfbPacket::Constant(ConstantReport {
+                block_index: reader.u8(0)?,
+                magnitude: reader.i16(1)?,
+            }),
+            0x06 => FfbPacket::Ramp(RampReport {
+                block_index: reader.u8(0)?,
+                start: reader.i16(1)?,
+                end: reader.i16(3)?,
+            }),
+            0x0A => FfbPacket::EffectOperation(EffectOperationReport {
+                block_index: reader.u8(0)?,
+                operation: EffectOperation::try_from(reader.u8(1)?)?,
+                loop_count: reader.u8(2)?,
+            }),
+            0x0B => FfbPacket::BlockFree(reader.u8(0)?),
+            0x0C => FfbPacket::DeviceControl(DeviceControl::try_from(reader.u8(0)?)?),
+            0x0D => FfbPacket::DeviceGain(reader.u8(0)?),
+            0x11 => FfbPacket::CreateNewEffect(EffectType::try_from(reader.u8(0)?)?),
+            report_id => return Err(Error::Ffb(FfbError::UnsupportedReport(report_id))),
+        };
+
+        Ok(packet)
+    }
+}
+
+/// Bounds-checked little-endian reads from a report payload.
+struct Reader<'a> {
+    report_id: u8,
+    payload: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N], Error> {
+        match self.payload.get(offset..offset + N) {
+            Some(bytes) => Ok(bytes.try_into().expect("slice has length N")),
+            None => Err(Error::Ffb(FfbError::PacketTooShort(
+                self.report_id,
+                offset + N + 1,
+                self.payload.len() + 1,
+            ))),
+        }
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, Error> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.bytes(offset)?))
+    }
+
+    fn i16(&self, offset: usize) -> Result<i16, Error> {
+        Ok(i16::from_le_bytes(self.bytes(offset)?))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.bytes(offset)?))
+    }
+}
+
+/// Parsed FFB packet together with the ID of the device it is addressed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FfbReport {
+    pub device_id: u32,
+    pub packet: FfbPacket,
+}
+
+/// Typed FFB report sent by a game to a vJoy device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FfbPacket {
+    Effect(EffectReport),
+    Envelope(EnvelopeReport),
+    Condition(ConditionReport),
+    Periodic(PeriodicReport),
+    Constant(ConstantReport),
+    Ramp(RampReport),
+    EffectOperation(EffectOperationReport),
+    DeviceControl(DeviceControl),
+    /// Global device gain in the range 0..=255.
+    DeviceGain(u8),
+    /// Effect block index to free.
+    BlockFree(u8),
+    /// Request to allocate a block index for a new effect of this type.
+    CreateNewEffect(EffectType),
+}
+
+impl FfbPacket {
+    /// Effect block index the packet refers to, if any.
+    #[profiling::function]
+    pub fn block_index(&self) -> Option<u8> {
+        match self {
+            FfbPacket::Effect(r) => Some(r.block_index),
+            FfbPacket::Envelope(r) => Some(r.block_index),
+            FfbPacket::Condition(r) => Some(r.block_index),
+            FfbPacket::Periodic(r) => Some(r.block_index),
+            FfbPacket::Constant(r) => Some(r.block_index),
+            FfbPacket::Ramp(r) => Some(r.block_index),
+            FfbPacket::EffectOperation(r) => Some(r.block_index),
+            FfbPacket::BlockFree(block_index) => Some(*block_index),
+            FfbPacket::DeviceControl(_)
+            | FfbPacket::DeviceGain(_)
+            | FfbPacket::CreateNewEffect(_) => None,
+        }
+    }
+}
+
+impl Display for FfbPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FfbPacket::Effect(r) => f.write_fmt(format_args!(
+                "Effect report: block {} | type: {} | duration: {} ms | gain: {}",
+                r.block_index, r.effect_type, r.duration, r.gain
+            )),
+            FfbPacket::Envelope(r) => f.write_fmt(format_args!(
+                "Envelope report: block {} | attack: {} over {} ms | fade: {} over {} ms",
+                r.block_index, r.attack_level, r.attack_time, r.fade_level, r.fade_time
+            )),
+            FfbPacket::Condition(r) => f.write_fmt(format_args!(
+                "Condition report: block {} | {} axis | offset: {} | coefficients: {}/{}",
+                r.block_index,
+                if r.is_y { "Y" } else { "X" },
+                r.center_point_offset,
+                r.positive_coefficient,
+                r.negative_coefficient
+            )),
+            FfbPacket::Periodic(r) => f.write_fmt(format_args!(
+                "Periodic report: block {} | magnitude: {} | offset: {} | period: {} ms",
+                r.block_index, r.magnitude, r.offset, r.period
+            )),
+            FfbPacket::Constant(r) => f.write_fmt(format_args!(
+                "Constant force report: block {} | magnitude: {}",
+                r.block_index, r.magnitude
+            )),
+            FfbPacket::Ramp(r) => f.write_fmt(format_args!(
+                "Ramp force report: block {} | start: {} | end: {}",
+                r.block_index, r.start, r.end
+            )),
+            FfbPacket::EffectOperation(r) => f.write_fmt(format_args!(
+                "Effect operation: block {} | {} | loop count: {}",
+                r.block_index, r.operation, r.loop_count
+            )),
+            FfbPacket::DeviceControl(c) => f.write_fmt(format_args!("Device control: {}", c)),
+            FfbPacket::DeviceGain(g) => f.write_fmt(format_args!("Device gain: {}", g)),
+            FfbPacket::BlockFree(b) => f.write_fmt(format_args!("Block free: block {}", b)),
+            FfbPacket::CreateNewEffect(t) => f.write_fmt(format_args!("Create new effect: {}", t)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(u8)]
+pub enum EffectType {
+    #[default]
+    Constant = 1,
+    Ramp = 2,
+    Square = 3,
+    Sine = 4,
+    Triangle = 5,
+    SawtoothUp = 6,
+    SawtoothDown = 7,
+    Spring = 8,
+    Damper = 9,
+    Inertia = 10,
+    Friction = 11,
+    Custom = 12,
+}
+
+impl EffectType {
+    #[profiling::function]
+    pub fn is_periodic(&self) -> bool {
+        matches!(
+            self,
+            EffectType::Square
+                | EffectType::Sine
+                | EffectType::Triangle
+                | EffectType::SawtoothUp
+                | EffectType::SawtoothDown
+        )
+    }
+
+    #[profiling::function]
+    pub fn is_condition(&self) -> bool {
+        matches!(
+            self,
+            EffectType::Spring | EffectType::Damper | EffectType::Inertia | EffectType::Friction
+        )
+    }
+}
+
+impl TryFrom<u8> for EffectType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let effect_type = match value {
+            1 => EffectType::Constant,
+            2 => EffectType::Ramp,
+            3 => EffectType::Square,
+            4 => EffectType::Sine,
+            5 => EffectType::Triangle,
+            6 => EffectType::SawtoothUp,
+            7 => EffectType::SawtoothDown,
+            8 => EffectType::Spring,
+            9 => EffectType::Damper,
+            10 => EffectType::Inertia,
+            11 => EffectType::Friction,
+            12 => EffectType::Custom,
+            value => {
+                return Err(Error::Ffb(FfbError::InvalidValue(
+                    "effect type",
+                    value as u32,
+                )));
+            }
+        };
+
+        Ok(effect_type)
+    }
+}
+
+impl Display for EffectType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            EffectType::Constant => "constant",
+            EffectType::Ramp => "ramp",
+            EffectType::Square => "square",
+            EffectType::Sine => "sine",
+            EffectType::Triangle => "triangle",
+            EffectType::SawtoothUp => "sawtooth up",
+            EffectType::SawtoothDown => "sawtooth down",
+            EffectType::Spring => "spring",
+            EffectType::Damper => "damper",
+            EffectType::Inertia => "inertia",
+            EffectType::Friction => "friction",
+            EffectType::Custom => "custom",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum EffectOperation {
+    Start = 1,
+    /// Start this effect and stop all others.
+    Solo = 2,
+    Stop = 3,
+}
+
+impl TryFrom<u8> for EffectOperation {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(EffectOperation::Start),
+            2 => Ok(EffectOperation::Solo),
+            3 => Ok(EffectOperation::Stop),
+            value => Err(Error::Ffb(FfbError::InvalidValue(
+                "effect operation",
+                value as u32,
+            ))),
+        }
+    }
+}
+
+impl Display for EffectOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            EffectOperation::Start => "start",
+            EffectOperation::Solo => "solo",
+            EffectOperation::Stop => "stop",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum DeviceControl {
+    EnableActuators = 1,
+    DisableActuators = 2,
+    StopAllEffects = 3,
+    /// Clears the paused state, enables all actuators and frees all effects.
+    Reset = 4,
+    Pause = 5,
+    Continue = 6,
+}
+
+impl TryFrom<u8> for DeviceControl {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(DeviceControl::EnableActuators),
+            2 => Ok(DeviceControl::DisableActuators),
+            3 => Ok(DeviceControl::StopAllEffects),
+            4 => Ok(DeviceControl::Reset),
+            5 => Ok(DeviceControl::Pause),
+            6 => Ok(DeviceControl::Continue),
+            value => Err(Error::Ffb(FfbError::InvalidValue(
+                "device control",
+                value as u32,
+            ))),
+        }
+    }
+}
+
+impl Display for DeviceControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            DeviceControl::EnableActuators => "enable actuators",
+            DeviceControl::DisableActuators => "disable actuators",
+            DeviceControl::StopAllEffects => "stop all effects",
+            DeviceControl::Reset => "reset",
+            DeviceControl::Pause => "pause",
+            DeviceControl::Continue => "continue",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+/// Set effect report: common parameters of an effect.
+///
+/// `duration`, `trigger_repeat`, `sample_period` and `start_delay` are in milliseconds, a duration of `0xFFFF` is infinite.
+/// `axes_enabled` holds one bit per axis (bit 0: X, bit 1: Y).
+/// For `polar` directions, `direction_x` holds the angle with 0..=0x7FFF corresponding to 0..360°.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EffectReport {
+    pub block_index: u8,
+    pub effect_type: EffectType,
+    pub duration: u16,
+    pub trigger_repeat: u16,
+    pub sample_period: u16,
+    pub start_delay: u16,
+    pub gain: u8,
+    pub trigger_button: u8,
+    pub axes_enabled: u8,
+    pub polar: bool,
+    pub direction_x: u16,
+    pub direction_y: u16,
+}
+
+impl EffectReport {
+    /// Effect duration or `None` if infinite.
+    #[profiling::function]
+    pub fn duration(&self) -> Option<Duration> {
+        if self.duration == 0xFFFF {
+            return None;
+        }
+
+        Some(Duration::from_millis(self.duration as u64))
+    }
+
+    #[profiling::function]
+    pub fn start_delay(&self) -> Duration {
+        Duration::from_millis(self.start_delay as u64)
+    }
+
+    /// Polar direction in degrees clockwise from North or `None` for cartesian directions.
+    #[profiling::function]
+    pub fn direction_degrees(&self) -> Option<f32> {
+        if !self.polar {
+            return None;
+        }
+
+        Some(self.direction_x as f32 * 360.0 / 0x8000 as f32)
+    }
+}
+
+/// Set envelope report. Levels are in the range 0..=10000, times in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct EnvelopeReport {
+    pub block_index: u8,
+    pub attack_level: u16,
+    pub fade_level: u16,
+    pub attack_time: u32,
+    pub fade_time: u32,
+}
+
+/// Set condition report for one axis of a spring, damper, inertia or friction effect.
+///
+/// Offset and coefficients are in the range -10000..=10000, saturations in 0..=10000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ConditionReport {
+    pub block_index: u8,
+    pub is_y: bool,
+    pub center_point_offset: i16,
+    pub positive_coefficient: i16,
+    pub negative_coefficient: i16,
+    pub positive_saturation: u16,
+    pub negative_saturation: u16,
+    pub dead_band: u16,
+}
+
+/// Set periodic report. Magnitude is in the range 0..=10000, offset in -10000..=10000,
+/// phase in 1/100° (0..=35999) and period in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PeriodicReport {
+    pub block_index: u8,
+    pub magnitude: u16,
+    pub offset: i16,
+    pub phase: u16,
+    pub period: u32,
+}
+
+/// Set constant force report. Magnitude is in the range -10000..=10000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ConstantReport {
+    pub block_index: u8,
+    pub magnitude: i16,
+}
+
+/// Set ramp force report. Start and end are in the range -10000..=10000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RampReport {
+    pub block_index: u8,
+    pub start: i16,
+    pub end: i16,
+}
+
+/// Effect operation report. A loop count of 0xFF repeats the effect infinitely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EffectOperationReport {
+    pub block_index: u8,
+    pub operation: EffectOperation,
+    pub loop_count: u8,
+}
@@ -0,0 +1,219 @@
+use crate::ffb::synth::{FULL_SCALE, ramp_value, with_envelope};
+use crate::ffb::{Effect, EffectType, FfbEffectManager};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Intensities of the two rumble motors of a gamepad in the range 0.0..=1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rumble {
+    pub low_frequency: f32,
+    pub high_frequency: f32,
+}
+
+impl Display for Rumble {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Rumble low frequency: {:.3} | high frequency: {:.3}",
+            self.low_frequency, self.high_frequency
+        ))
+    }
+}
+
+/// Output for rumble intensities, e.g. a gamepad driver.
+///
+/// Implemented for closures taking a [Rumble].
+pub trait RumbleSink {
+    fn rumble(&mut self, rumble: Rumble);
+}
+
+impl<F: FnMut(Rumble)> RumbleSink for F {
+    fn rumble(&mut self, rumble: Rumble) {
+        self(rumble)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RumbleMotor {
+    LowFrequency,
+    HighFrequency,
+}
+
+/// Effects a [RumbleRule] applies to.
+///
+/// - `Periodic`: periodic effects with a frequency in the range `min_frequency..max_frequency` Hz. Effects with a period of 0 have a frequency of 0 Hz.
+/// - `Constant`: constant force effects.
+/// - `Ramp`: ramp force effects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RumbleSource {
+    Periodic {
+        min_frequency: f32,
+        max_frequency: f32,
+    },
+    Constant,
+    Ramp,
+}
+
+/// Routes matching effects to a motor. The intensity is the effect magnitude, scaled by `gain`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RumbleRule {
+    pub source: RumbleSource,
+    pub motor: RumbleMotor,
+    pub gain: f32,
+}
+
+/// Downmixes the active effects of an [FfbEffectManager] into dual-motor rumble.
+///
+/// Each active effect is routed by the first [RumbleRule] that matches it. Periodic effects contribute their magnitude
+/// (not the instantaneous waveform value), constant and ramp effects the absolute value of their force.
+/// Envelopes, effect gain and device gain are applied, and the contributions per motor are summed and clamped to 1.0.
+/// Effects without a matching rule, e.g. conditions, are ignored.
+///
+/// The [default](Self::default) rules send periodic effects below 20 Hz as well as constant and ramp forces to the low-frequency motor,
+/// and faster periodic effects to the high-frequency motor.
+///
+/// ```no_run
+/// # use vjoy::ffb::{FfbEffectManager, Rumble, RumbleMixer};
+/// # let manager = FfbEffectManager::new(1);
+/// let mixer = RumbleMixer::default();
+/// let start = std::time::Instant::now();
+///
+/// mixer.update(&manager, start.elapsed(), &mut |rumble: Rumble| println!("{rumble}"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RumbleMixer {
+    rules: Vec<RumbleRule>,
+}
+
+impl Default for RumbleMixer {
+    fn default() -> Self {
+        Self::new(vec![
+            RumbleRule {
+                source: RumbleSource::Periodic {
+                    min_frequency: 0.0,
+                    max_frequency: 20.0,
+                },
+                motor: RumbleMotor::LowFrequency,
+                gain: 1.0,
+            },
+            RumbleRule {
+                source: RumbleSource::Periodic {
+                    min_frequency: 20.0,
+                    max_frequency: f32::INFINITY,
+                },
+                motor: RumbleMotor::HighFrequency,
+                gain: 1.0,
+            },
+            RumbleRule {
+                source: RumbleSource::Constant,
+                motor: RumbleMotor::LowFrequency,
+                gain: 1.0,
+            },
+            RumbleRule {
+                source: RumbleSource::Ramp,
+                motor: RumbleMotor::LowFrequency,
+                gain: 1.0,
+            },
+        ])
+    }
+}
+
+impl RumbleMixer {
+    #[profiling::function]
+    pub fn new(rules: Vec<RumbleRule>) -> Self {
+        Self { rules }
+    }
+
+    #[profiling::function]
+    pub fn rules(&self) -> &[RumbleRule] {
+        &self.rules
+    }
+
+    /// Rumble intensities of all active effects at `now`.
+    #[profiling::function]
+    pub fn mix(&self, manager: &FfbEffectManager, now: Duration) -> Rumble {
+        let device_gain = manager.gain() as f32 / u8::MAX as f32;
+
+        let mut rumble = Rumble::default();
+        for (effect, time) in manager.active_effects(now) {
+            let Some((rule, magnitude)) = self.route(effect, time) else {
+                continue;
+            };
+
+            let intensity = magnitude / FULL_SCALE * rule.gain * device_gain;
+            match rule.motor {
+                RumbleMotor::LowFrequency => rumble.low_frequency += intensity,
+                RumbleMotor::HighFrequency => rumble.high_frequency += intensity,
+            }
+        }
+
+        rumble.low_frequency = rumble.low_frequency.clamp(0.0, 1.0);
+        rumble.high_frequency = rumble.high_frequency.clamp(0.0, 1.0);
+        rumble
+    }
+
+    /// Mixes the active effects at `now` and sends the result to the sink.
+    #[profiling::function]
+    pub fn update<S: RumbleSink + ?Sized>(
+        &self,
+        manager: &FfbEffectManager,
+        now: Duration,
+        sink: &mut S,
+    ) {
+        sink.rumble(self.mix(manager, now));
+    }
+
+    /// First matching rule for the effect and its absolute magnitude including effect gain.
+    #[profiling::function]
+    fn route(&self, effect: &Effect, time: Duration) -> Option<(&RumbleRule, f32)> {
+        let report = effect.report()?;
+        let duration = report.duration();
+        let gain = report.gain as f32 / u8::MAX as f32;
+
+        let (frequency, magnitude) = match effect.effect_type() {
+            EffectType::Constant => {
+                let constant = effect.constant()?;
+                let magnitude =
+                    with_envelope(constant.magnitude as f32, effect.envelope(), time, duration);
+                (None, magnitude)
+            }
+            EffectType::Ramp => {
+                let ramp = effect.ramp()?;
+                let value = ramp_value(ramp, time, duration);
+                (
+                    None,
+                    with_envelope(value, effect.envelope(), time, duration),
+                )
+            }
+            effect_type if effect_type.is_periodic() => {
+                let periodic = effect.periodic()?;
+                let frequency = if periodic.period == 0 {
+                    0.0
+                } else {
+                    1000.0 / periodic.period as f32
+                };
+                let magnitude =
+                    with_envelope(periodic.magnitude as f32, effect.envelope(), time, duration);
+                (Some(frequency), magnitude)
+            }
+            _ => return None,
+        };
+
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| match (rule.source, frequency) {
+                (
+                    RumbleSource::Periodic {
+                        min_frequency,
+                        max_frequency,
+                    },
+                    Some(frequency),
+                ) => (min_frequency..max_frequency).contains(&frequency),
+                (RumbleSource::Constant, None) => effect.effect_type() == EffectType::Constant,
+                (RumbleSource::Ramp, None) => effect.effect_type() == EffectType::Ramp,
+                _ => false,
+            })?;
+
+        Some((rule, magnitude.abs() * gain))
+    }
+}
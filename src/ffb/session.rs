@@ -0,0 +1,282 @@
+use crate::error::{Error, FfbError};
+use crate::ffb::{FfbEffectManager, RawFfbPacket};
+use log::warn;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// First line of every FFB session file.
+pub const FFB_SESSION_HEADER: &str = "# vjoy ffb session v1";
+
+/// A raw FFB packet with the time it was received, relative to the start of the session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct RecordedFfbPacket {
+    pub timestamp: Duration,
+    pub packet: RawFfbPacket,
+}
+
+impl RecordedFfbPacket {
+    #[profiling::function]
+    fn to_line(&self) -> String {
+        let mut line = format!("{} {:08x} ", self.timestamp.as_micros(), self.packet.cmd);
+        for byte in &self.packet.data {
+            let _ = write!(line, "{:02x}", byte);
+        }
+
+        line
+    }
+
+    #[profiling::function]
+    fn from_line(line_number: usize, line: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| {
+            Error::Ffb(FfbError::InvalidSessionLine(
+                line_number,
+                reason.to_string(),
+            ))
+        };
+
+        let mut fields = line.split_whitespace();
+        let (Some(timestamp), Some(cmd), Some(data), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid("expected timestamp, command and data"));
+        };
+
+        let timestamp = timestamp
+            .parse::<u64>()
+            .map_err(|_| invalid("invalid timestamp"))?;
+        let cmd = u32::from_str_radix(cmd, 16).map_err(|_| invalid("invalid command"))?;
+        if data.len() % 2 != 0 {
+            return Err(invalid("odd number of hex digits in data"));
+        }
+        let data = (0..data.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&data[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid("invalid hex data"))?;
+
+        Ok(Self {
+            timestamp: Duration::from_micros(timestamp),
+            packet: RawFfbPacket::new(cmd, data),
+        })
+    }
+}
+
+/// A captured sequence of raw FFB packets.
+///
+/// Sessions are stored as text: a [header](FFB_SESSION_HEADER) line followed by one packet per line with the timestamp in microseconds,
+/// the `FFB_DATA::cmd` and the packet data in hex, e.g. `1500 000b000f 1a0101ff`.
+/// The header is required. Empty lines and other lines starting with `#` are ignored, so captures can be annotated by hand.
+///
+/// Packets are stored unparsed, so packets the parser rejects are part of the capture as well.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FfbSession {
+    pub packets: Vec<RecordedFfbPacket>,
+}
+
+impl FfbSession {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[profiling::function]
+    pub fn push(&mut self, timestamp: Duration, packet: RawFfbPacket) {
+        self.packets.push(RecordedFfbPacket { timestamp, packet });
+    }
+
+    #[profiling::function]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    #[profiling::function]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        self.write_to(LineWriter::new(file))
+    }
+
+    #[profiling::function]
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut session = Self::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            let line = line.trim();
+            if index == 0 {
+                if line != FFB_SESSION_HEADER {
+                    return Err(Error::Ffb(FfbError::InvalidSessionLine(
+                        1,
+                        format!("expected header \"{}\"", FFB_SESSION_HEADER),
+                    )));
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            session
+                .packets
+                .push(RecordedFfbPacket::from_line(index + 1, line)?);
+        }
+
+        Ok(session)
+    }
+
+    #[profiling::function]
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "{}", FFB_SESSION_HEADER).map_err(Error::Io)?;
+        for packet in &self.packets {
+            writeln!(writer, "{}", packet.to_line()).map_err(Error::Io)?;
+        }
+
+        writer.flush().map_err(Error::Io)
+    }
+
+    #[profiling::function]
+    pub fn replayer(&self) -> FfbReplayer<'_> {
+        FfbReplayer {
+            packets: &self.packets,
+            position: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Applies all packets to the manager and returns the number of applied packets. See [FfbReplayer::advance].
+    #[profiling::function]
+    pub fn replay(&self, manager: &mut FfbEffectManager) -> usize {
+        self.replayer().advance(manager, Duration::MAX)
+    }
+}
+
+/// Writes raw FFB packets to a session file while they arrive, so a capture survives a crashing game or feeder.
+///
+/// Timestamps are measured from the creation of the recorder.
+/// Use [VJoy::record_ffb](crate::VJoy::record_ffb) to record all packets the driver sends.
+///
+/// ```no_run
+/// # use vjoy::Error;
+/// # use vjoy::ffb::{FfbRecorder, RawFfbPacket, IOCTL_HID_WRITE_REPORT};
+/// let mut recorder = FfbRecorder::create("capture.ffb")?;
+/// recorder.record(&RawFfbPacket::new(IOCTL_HID_WRITE_REPORT, vec![0x1D, 0x80]))?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug)]
+pub struct FfbRecorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl FfbRecorder<LineWriter<File>> {
+    /// Creates or truncates the session file at `path`.
+    #[profiling::function]
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        Self::new(LineWriter::new(file))
+    }
+}
+
+impl<W: Write> FfbRecorder<W> {
+    #[profiling::function]
+    pub fn new(mut writer: W) -> Result<Self, Error> {
+        writeln!(writer, "{}", FFB_SESSION_HEADER).map_err(Error::Io)?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Records a packet with the time elapsed since the recorder was created.
+    #[profiling::function]
+    pub fn record(&mut self, packet: &RawFfbPacket) -> Result<(), Error> {
+        let timestamp = self.start.elapsed();
+        self.record_at(timestamp, packet)
+    }
+
+    #[profiling::function]
+    pub fn record_at(&mut self, timestamp: Duration, packet: &RawFfbPacket) -> Result<(), Error> {
+        let recorded = RecordedFfbPacket {
+            timestamp,
+            packet: packet.clone(),
+        };
+        writeln!(self.writer, "{}", recorded.to_line()).map_err(Error::Io)
+    }
+
+    #[profiling::function]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Feeds the packets of an [FfbSession] into an [FfbEffectManager] in recorded order and timing.
+///
+/// Advance to the times at which the effects should be inspected or synthesized, e.g. in fixed steps for a force graph.
+#[derive(Debug, Clone)]
+pub struct FfbReplayer<'a> {
+    packets: &'a [RecordedFfbPacket],
+    position: usize,
+    skipped: usize,
+}
+
+impl FfbReplayer<'_> {
+    /// Timestamp of the next packet or `None` if all packets were replayed.
+    #[profiling::function]
+    pub fn next_timestamp(&self) -> Option<Duration> {
+        self.packets
+            .get(self.position)
+            .map(|packet| packet.timestamp)
+    }
+
+    #[profiling::function]
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.packets.len()
+    }
+
+    /// Number of packets skipped since the last rewind because they failed to parse.
+    #[profiling::function]
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    #[profiling::function]
+    pub fn rewind(&mut self) {
+        self.position = 0;
+        self.skipped = 0;
+    }
+
+    /// Applies all remaining packets recorded up to and including `until` to the manager, each at its recorded timestamp.
+    ///
+    /// Returns the number of applied packets. Packets for other devices count as applied but are ignored by the manager.
+    /// Like the live dispatch, packets that fail to parse, e.g. unsupported reports, are skipped with a warning and counted in [skipped](Self::skipped).
+    #[profiling::function]
+    pub fn advance(&mut self, manager: &mut FfbEffectManager, until: Duration) -> usize {
+        let mut applied = 0;
+        while let Some(recorded) = self.packets.get(self.position) {
+            if recorded.timestamp > until {
+                break;
+            }
+
+            self.position += 1;
+            match recorded.packet.report() {
+                Ok(report) => {
+                    manager.apply_report(&report, recorded.timestamp);
+                    applied += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "Skipped FFB packet at {} us: {}",
+                        recorded.timestamp.as_micros(),
+                        e
+                    );
+                    self.skipped += 1;
+                }
+            }
+        }
+
+        applied
+    }
+}
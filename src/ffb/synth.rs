@@ -0,0 +1,371 @@
+use crate::axis::AxisKind;
+use crate::device::Device;
+use crate::error::{AppError, Error};
+use crate::ffb::{
+    ConditionReport, Effect, EffectReport, EffectType, EnvelopeReport, FfbEffectManager, RampReport,
+};
+use std::f32::consts::TAU;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Full scale of effect magnitudes, coefficients and levels in FFB reports.
+pub(crate) const FULL_SCALE: f32 = 10000.0;
+
+/// Position, velocity and acceleration of one axis.
+///
+/// Positions are normalised to -1.0..=1.0 (see [Axis::normalized](crate::Axis::normalized)),
+/// velocities and accelerations are in normalised units per second (squared).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AxisMotion {
+    pub position: f32,
+    pub velocity: f32,
+    pub acceleration: f32,
+}
+
+/// Resulting force per axis in the range -1.0..=1.0. Positive values push towards the axis maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FfbForce {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Display for FfbForce {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Force x: {:.3} | y: {:.3}", self.x, self.y))
+    }
+}
+
+/// Parameters for an [FfbSynthesizer].
+///
+/// - `x_axis`/`y_axis`: kinds of the device axes that receive the X and Y forces, found regardless of their position on the device. Without a Y axis, Y forces are still computed from a centered axis at rest.
+/// - `max_velocity`: velocity at which damper and friction conditions reach their full coefficient.
+/// - `max_acceleration`: acceleration at which inertia conditions reach their full coefficient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SynthesizerConfig {
+    pub x_axis: AxisKind,
+    pub y_axis: Option<AxisKind>,
+    pub max_velocity: f32,
+    pub max_acceleration: f32,
+}
+
+impl Default for SynthesizerConfig {
+    fn default() -> Self {
+        Self {
+            x_axis: AxisKind::X,
+            y_axis: Some(AxisKind::Y),
+            max_velocity: 4.0,
+            max_acceleration: 40.0,
+        }
+    }
+}
+
+/// Computes the force per axis from the active effects of an [FfbEffectManager].
+///
+/// Supports constant, ramp and periodic forces with envelopes, as well as spring, damper, inertia and friction conditions.
+/// Effect gain and device gain are applied, the sum of all effects is clamped to -1.0..=1.0. Custom effects produce no force.
+///
+/// Effects on a single axis apply their magnitude to that axis. Effects on both axes with a polar direction
+/// are split along the direction, with 0° pointing towards +Y and 90° towards +X.
+///
+/// Velocities and accelerations are derived from the axis positions passed to [update](Self::update),
+/// so call it on every tick. [force](Self::force) computes the force for an explicit motion without any state.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, Error};
+/// # use vjoy::ffb::{FfbEffectManager, FfbSynthesizer, SynthesizerConfig};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// # let manager = FfbEffectManager::new(1);
+/// let mut synthesizer = FfbSynthesizer::new(SynthesizerConfig::default());
+/// let start = std::time::Instant::now();
+///
+/// let device_1 = vjoy.get_device_state_ref(1)?;
+/// let force = synthesizer.update(&manager, device_1, start.elapsed())?;
+/// println!("{force}");
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FfbSynthesizer {
+    config: SynthesizerConfig,
+    previous: Option<(Duration, [AxisMotion; 2])>,
+}
+
+impl FfbSynthesizer {
+    #[profiling::function]
+    pub fn new(config: SynthesizerConfig) -> Self {
+        Self {
+            config,
+            previous: None,
+        }
+    }
+
+    #[profiling::function]
+    pub fn config(&self) -> SynthesizerConfig {
+        self.config
+    }
+
+    /// Reads the axis positions of the device at `now` and derives their velocities and accelerations from the previous call.
+    ///
+    /// Timestamps must not decrease between calls.
+    #[profiling::function]
+    pub fn track(&mut self, device: &Device, now: Duration) -> Result<[AxisMotion; 2], Error> {
+        let position = |kind: AxisKind| -> Result<f32, Error> {
+            device
+                .axes()
+                .find(|axis| axis.kind() == Some(kind))
+                .map(|axis| axis.normalized())
+                .ok_or(Error::App(AppError::AxisNotFound(device.id, kind.id())))
+        };
+        let positions = [
+            position(self.config.x_axis)?,
+            match self.config.y_axis {
+                Some(kind) => position(kind)?,
+                None => 0.0,
+            },
+        ];
+
+        let mut motion = [AxisMotion::default(); 2];
+        for (i, motion) in motion.iter_mut().enumerate() {
+            motion.position = positions[i];
+
+            let Some((previous_at, previous)) = self.previous else {
+                continue;
+            };
+            let dt = now.saturating_sub(previous_at).as_secs_f32();
+            if dt > 0.0 {
+                motion.velocity = (motion.position - previous[i].position) / dt;
+                motion.acceleration = (motion.velocity - previous[i].velocity) / dt;
+            } else {
+                motion.velocity = previous[i].velocity;
+                motion.acceleration = previous[i].acceleration;
+            }
+        }
+
+        self.previous = Some((now, motion));
+        Ok(motion)
+    }
+
+    /// Tracks the device axes and computes the force at `now`. See [track](Self::track) and [force](Self::force).
+    #[profiling::function]
+    pub fn update(
+        &mut self,
+        manager: &FfbEffectManager,
+        device: &Device,
+        now: Duration,
+    ) -> Result<FfbForce, Error> {
+        let motion = self.track(device, now)?;
+        Ok(self.force(manager, &motion, now))
+    }
+
+    /// Force of all active effects at `now` for the given X and Y axis motion.
+    #[profiling::function]
+    pub fn force(
+        &self,
+        manager: &FfbEffectManager,
+        motion: &[AxisMotion; 2],
+        now: Duration,
+    ) -> FfbForce {
+        let mut force = [0.0f32; 2];
+        for (effect, time) in manager.active_effects(now) {
+            let effect_force = self.effect_force(effect, time, motion);
+            force[0] += effect_force[0];
+            force[1] += effect_force[1];
+        }
+
+        let gain = manager.gain() as f32 / u8::MAX as f32;
+        FfbForce {
+            x: (force[0] * gain / FULL_SCALE).clamp(-1.0, 1.0),
+            y: (force[1] * gain / FULL_SCALE).clamp(-1.0, 1.0),
+        }
+    }
+
+    /// Force of a single effect in the range of the reports (-10000..=10000) before device gain.
+    #[profiling::function]
+    fn effect_force(&self, effect: &Effect, time: Duration, motion: &[AxisMotion; 2]) -> [f32; 2] {
+        let Some(report) = effect.report() else {
+            return [0.0; 2];
+        };
+        let gain = report.gain as f32 / u8::MAX as f32;
+
+        if effect.effect_type().is_condition() {
+            let mut force = [0.0; 2];
+            for (i, force) in force.iter_mut().enumerate() {
+                if report.axes_enabled & (1 << i) == 0 {
+                    continue;
+                }
+                if let Some(condition) = effect.conditions()[i] {
+                    *force =
+                        self.condition_force(effect.effect_type(), &condition, &motion[i]) * gain;
+                }
+            }
+            return force;
+        }
+
+        let duration = report.duration();
+        let magnitude = match effect.effect_type() {
+            EffectType::Constant => effect.constant().map(|constant| {
+                with_envelope(constant.magnitude as f32, effect.envelope(), time, duration)
+            }),
+            EffectType::Ramp => effect.ramp().map(|ramp| {
+                with_envelope(
+                    ramp_value(ramp, time, duration),
+                    effect.envelope(),
+                    time,
+                    duration,
+                )
+            }),
+            effect_type if effect_type.is_periodic() => effect.periodic().map(|periodic| {
+                let cycles = if periodic.period == 0 {
+                    0.0
+                } else {
+                    time.as_secs_f32() * 1000.0 / periodic.period as f32
+                };
+                let phase = (cycles + periodic.phase as f32 / 36000.0).fract();
+                let magnitude =
+                    with_envelope(periodic.magnitude as f32, effect.envelope(), time, duration);
+                periodic.offset as f32 + magnitude * waveform(effect_type, phase)
+            }),
+            _ => None,
+        };
+
+        let magnitude = magnitude.unwrap_or_default() * gain;
+        let direction = direction(report);
+        [magnitude * direction[0], magnitude * direction[1]]
+    }
+
+    #[profiling::function]
+    fn condition_force(
+        &self,
+        effect_type: EffectType,
+        condition: &ConditionReport,
+        motion: &AxisMotion,
+    ) -> f32 {
+        let metric = match effect_type {
+            EffectType::Spring => motion.position,
+            EffectType::Damper | EffectType::Friction => motion.velocity / self.config.max_velocity,
+            EffectType::Inertia => motion.acceleration / self.config.max_acceleration,
+            _ => return 0.0,
+        };
+        let metric = (metric * FULL_SCALE).clamp(-FULL_SCALE, FULL_SCALE);
+
+        // A saturation of 0 is sent by games that don't limit the force
+        let saturation = |saturation: u16| {
+            if saturation == 0 {
+                FULL_SCALE
+            } else {
+                saturation as f32
+            }
+        };
+        let center = condition.center_point_offset as f32;
+        let dead_band = condition.dead_band as f32;
+        let positive = condition.positive_coefficient as f32;
+        let negative = condition.negative_coefficient as f32;
+
+        // Friction opposes movement with a constant force, all other conditions scale with the distance from the dead band
+        let force = if metric > center + dead_band {
+            let distance = match effect_type {
+                EffectType::Friction => FULL_SCALE,
+                _ => metric - center - dead_band,
+            };
+            (positive * distance / FULL_SCALE).clamp(
+                -saturation(condition.positive_saturation),
+                saturation(condition.positive_saturation),
+            )
+        } else if metric < center - dead_band {
+            let distance = match effect_type {
+                EffectType::Friction => -FULL_SCALE,
+                _ => metric - center + dead_band,
+            };
+            (negative * distance / FULL_SCALE).clamp(
+                -saturation(condition.negative_saturation),
+                saturation(condition.negative_saturation),
+            )
+        } else {
+            0.0
+        };
+
+        -force
+    }
+}
+
+/// Value of a ramp at `time`. Infinite ramps stay at their start value.
+pub(crate) fn ramp_value(ramp: &RampReport, time: Duration, duration: Option<Duration>) -> f32 {
+    let progress = match duration {
+        Some(duration) if !duration.is_zero() => {
+            (time.as_secs_f32() / duration.as_secs_f32()).clamp(0.0, 1.0)
+        }
+        _ => 0.0,
+    };
+
+    ramp.start as f32 + (ramp.end as f32 - ramp.start as f32) * progress
+}
+
+/// Applies the attack and fade of an envelope to a signed magnitude.
+pub(crate) fn with_envelope(
+    magnitude: f32,
+    envelope: Option<&EnvelopeReport>,
+    time: Duration,
+    duration: Option<Duration>,
+) -> f32 {
+    let Some(envelope) = envelope else {
+        return magnitude;
+    };
+
+    let sustain = magnitude.abs();
+    let elapsed = time.as_secs_f32() * 1000.0;
+    let remaining = duration.map(|duration| duration.as_secs_f32() * 1000.0 - elapsed);
+
+    let level = if envelope.attack_time > 0 && elapsed < envelope.attack_time as f32 {
+        let attack = envelope.attack_level as f32;
+        attack + (sustain - attack) * elapsed / envelope.attack_time as f32
+    } else if let Some(remaining) = remaining
+        && envelope.fade_time > 0
+        && remaining < envelope.fade_time as f32
+    {
+        let fade = envelope.fade_level as f32;
+        fade + (sustain - fade) * remaining.max(0.0) / envelope.fade_time as f32
+    } else {
+        sustain
+    };
+
+    level.copysign(magnitude)
+}
+
+/// Value of a periodic waveform in the range -1.0..=1.0 at a phase in the range 0.0..1.0.
+fn waveform(effect_type: EffectType, phase: f32) -> f32 {
+    match effect_type {
+        EffectType::Square => {
+            if phase < 0.5 {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        EffectType::Sine => (phase * TAU).sin(),
+        EffectType::Triangle => {
+            if phase < 0.25 {
+                4.0 * phase
+            } else if phase < 0.75 {
+                2.0 - 4.0 * phase
+            } else {
+                4.0 * phase - 4.0
+            }
+        }
+        EffectType::SawtoothUp => 2.0 * phase - 1.0,
+        EffectType::SawtoothDown => 1.0 - 2.0 * phase,
+        _ => 0.0,
+    }
+}
+
+/// Share of an effect's magnitude per axis.
+fn direction(report: &EffectReport) -> [f32; 2] {
+    let x = report.axes_enabled & 0b01 != 0;
+    let y = report.axes_enabled & 0b10 != 0;
+
+    match (x, y, report.direction_degrees()) {
+        (true, true, Some(degrees)) => {
+            let radians = degrees.to_radians();
+            [radians.sin(), radians.cos()]
+        }
+        (x, y, _) => [x as u8 as f32, y as u8 as f32],
+    }
+}
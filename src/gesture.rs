@@ -0,0 +1,328 @@
+use crate::button::ButtonState;
+use crate::device::Device;
+use crate::error::Error;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Gesture recognised on a single physical button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Gesture {
+    ShortPress,
+    LongPress,
+    DoubleTap,
+    TripleTap,
+}
+
+impl Display for Gesture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            Gesture::ShortPress => "short press",
+            Gesture::LongPress => "long press",
+            Gesture::DoubleTap => "double tap",
+            Gesture::TripleTap => "triple tap",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+/// Virtual button IDs that each gesture is mapped to.
+///
+/// Unmapped gestures are never emitted: without a [long_press](Self::long_press) mapping, holding the button is a short press,
+/// and without [double_tap](Self::double_tap)/[triple_tap](Self::triple_tap) mappings, short presses are emitted without waiting for further taps.
+/// A tap count below the highest mapped one that has no mapping itself, e.g. a double tap with only a triple tap mapped, is emitted as a short press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GestureMapping {
+    pub short_press: u8,
+    pub long_press: Option<u8>,
+    pub double_tap: Option<u8>,
+    pub triple_tap: Option<u8>,
+}
+
+impl GestureMapping {
+    #[profiling::function]
+    pub fn button_id(&self, gesture: Gesture) -> Option<u8> {
+        match gesture {
+            Gesture::ShortPress => Some(self.short_press),
+            Gesture::LongPress => self.long_press,
+            Gesture::DoubleTap => self.double_tap,
+            Gesture::TripleTap => self.triple_tap,
+        }
+    }
+
+    fn max_taps(&self) -> u8 {
+        if self.triple_tap.is_some() {
+            3
+        } else if self.double_tap.is_some() {
+            2
+        } else {
+            1
+        }
+    }
+}
+
+/// Timing parameters for a [GestureClassifier].
+///
+/// - `long_press_threshold`: hold time after which a press counts as a long press.
+/// - `multi_tap_window`: max. time between a release and the next press to count towards a double/triple tap.
+/// - `pulse_duration`: time the virtual button of a short press or tap gesture stays pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GestureConfig {
+    pub long_press_threshold: Duration,
+    pub multi_tap_window: Duration,
+    pub pulse_duration: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press_threshold: Duration::from_millis(500),
+            multi_tap_window: Duration::from_millis(250),
+            pulse_duration: Duration::from_millis(50),
+        }
+    }
+}
+
+/// A gesture emitted by a [GestureClassifier] together with the virtual button it maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GestureEvent {
+    pub gesture: Gesture,
+    pub button_id: u8,
+    pub timestamp: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    Pressed { since: Duration, taps: u8 },
+    LongPress,
+    AwaitingTap { taps: u8, deadline: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    None,
+    Pulse { button_id: u8, until: Duration },
+    Held { button_id: u8 },
+}
+
+/// Classifies one physical button stream into short press, long press and double/triple tap gestures,
+/// each mapped to its own virtual button.
+///
+/// The classifier is driven purely by the timestamps passed to [update](Self::update) - e.g. the elapsed time since an arbitrary `Instant` - and is therefore deterministic.
+/// Call [update](Self::update) with the current physical button state on every tick, then write the virtual buttons via [apply](Self::apply).
+///
+/// ```no_run
+/// # use vjoy::{VJoy, ButtonState, Error, GestureClassifier, GestureConfig, GestureMapping};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// # let physical_state = ButtonState::Pressed;
+/// let mapping = GestureMapping {
+///     short_press: 1,
+///     long_press: Some(2),
+///     double_tap: Some(3),
+///     triple_tap: None,
+/// };
+/// let mut classifier = GestureClassifier::new(mapping, GestureConfig::default());
+/// let start = std::time::Instant::now();
+///
+/// let device_1 = vjoy.get_device_state_mut(1)?;
+/// classifier.update(physical_state, start.elapsed());
+/// classifier.apply(device_1)?;
+/// vjoy.update_all_devices()?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureClassifier {
+    mapping: GestureMapping,
+    config: GestureConfig,
+    phase: Phase,
+    output: Output,
+    timestamp: Duration,
+}
+
+impl GestureClassifier {
+    #[profiling::function]
+    pub fn new(mapping: GestureMapping, config: GestureConfig) -> Self {
+        Self {
+            mapping,
+            config,
+            phase: Phase::Idle,
+            output: Output::None,
+            timestamp: Duration::ZERO,
+        }
+    }
+
+    #[profiling::function]
+    pub fn mapping(&self) -> GestureMapping {
+        self.mapping
+    }
+
+    #[profiling::function]
+    pub fn config(&self) -> GestureConfig {
+        self.config
+    }
+
+    /// Feeds the current physical button state at `timestamp` and returns a gesture if one was completed.
+    ///
+    /// Timestamps must not decrease between calls. A pending tap sequence is resolved once its multi-tap window has passed,
+    /// so keep calling this while the button is idle.
+    /// Holding the button for a long press during a tap sequence turns the sequence into the long press, the taps counted before it are discarded.
+    #[profiling::function]
+    pub fn update(&mut self, state: ButtonState, timestamp: Duration) -> Option<GestureEvent> {
+        let now = timestamp.max(self.timestamp);
+        self.timestamp = now;
+
+        if let Output::Pulse { until, .. } = self.output
+            && now >= until
+        {
+            self.output = Output::None;
+        }
+
+        match (self.phase, state) {
+            (Phase::Idle, ButtonState::Pressed) => {
+                self.phase = Phase::Pressed {
+                    since: now,
+                    taps: 0,
+                };
+                self.check_long_press(now)
+            }
+            (Phase::Idle, ButtonState::Released) => None,
+            (Phase::Pressed { .. }, ButtonState::Pressed) => self.check_long_press(now),
+            (Phase::Pressed { taps, .. }, ButtonState::Released) => {
+                let taps = taps + 1;
+                if taps >= self.mapping.max_taps() {
+                    self.phase = Phase::Idle;
+                    self.emit_taps(taps, now)
+                } else {
+                    self.phase = Phase::AwaitingTap {
+                        taps,
+                        deadline: now + self.config.multi_tap_window,
+                    };
+                    None
+                }
+            }
+            (Phase::LongPress, ButtonState::Pressed) => None,
+            (Phase::LongPress, ButtonState::Released) => {
+                self.phase = Phase::Idle;
+                self.output = Output::None;
+                None
+            }
+            (Phase::AwaitingTap { taps, deadline }, state) => {
+                if now > deadline {
+                    let event = self.emit_taps(taps, now);
+                    self.phase = match state {
+                        ButtonState::Pressed => Phase::Pressed {
+                            since: now,
+                            taps: 0,
+                        },
+                        ButtonState::Released => Phase::Idle,
+                    };
+                    event
+                } else {
+                    if state == ButtonState::Pressed {
+                        self.phase = Phase::Pressed { since: now, taps };
+                    }
+                    None
+                }
+            }
+        }
+    }
+
+    /// Current state of every mapped virtual button.
+    #[profiling::function]
+    pub fn outputs(&self) -> Vec<(u8, ButtonState)> {
+        let active = match self.output {
+            Output::None => None,
+            Output::Pulse { button_id, .. } | Output::Held { button_id } => Some(button_id),
+        };
+
+        let mut ids = vec![self.mapping.short_press];
+        ids.extend(
+            [
+                self.mapping.long_press,
+                self.mapping.double_tap,
+                self.mapping.triple_tap,
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        ids.sort_unstable();
+        ids.dedup();
+
+        ids.into_iter()
+            .map(|id| {
+                let state = if active == Some(id) {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Released
+                };
+                (id, state)
+            })
+            .collect()
+    }
+
+    /// Writes the current state of all mapped virtual buttons to the device.
+    ///
+    /// Only buttons whose state changed are written, so the device is not marked [dirty](Device::is_dirty) on idle ticks.
+    #[profiling::function]
+    pub fn apply(&self, device: &mut Device) -> Result<(), Error> {
+        for (button_id, state) in self.outputs() {
+            let current = device
+                .buttons()
+                .nth((button_id as usize).wrapping_sub(1))
+                .map(|button| button.get());
+            if current != Some(state) {
+                device.set_button(button_id, state)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resets the classifier to idle and releases all virtual buttons.
+    #[profiling::function]
+    pub fn reset(&mut self) {
+        self.phase = Phase::Idle;
+        self.output = Output::None;
+    }
+
+    fn check_long_press(&mut self, now: Duration) -> Option<GestureEvent> {
+        let Phase::Pressed { since, .. } = self.phase else {
+            return None;
+        };
+        let button_id = self.mapping.long_press?;
+
+        if now.saturating_sub(since) < self.config.long_press_threshold {
+            return None;
+        }
+
+        self.phase = Phase::LongPress;
+        self.output = Output::Held { button_id };
+        Some(GestureEvent {
+            gesture: Gesture::LongPress,
+            button_id,
+            timestamp: now,
+        })
+    }
+
+    fn emit_taps(&mut self, taps: u8, now: Duration) -> Option<GestureEvent> {
+        let gesture = match taps {
+            1 => Gesture::ShortPress,
+            2 => Gesture::DoubleTap,
+            _ => Gesture::TripleTap,
+        };
+        let (gesture, button_id) = match self.mapping.button_id(gesture) {
+            Some(button_id) => (gesture, button_id),
+            None => (Gesture::ShortPress, self.mapping.short_press),
+        };
+
+        self.output = Output::Pulse {
+            button_id,
+            until: now + self.config.pulse_duration,
+        };
+        Some(GestureEvent {
+            gesture,
+            button_id,
+            timestamp: now,
+        })
+    }
+}
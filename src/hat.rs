@@ -1,6 +1,9 @@
+use crate::error::{AppError, Error};
+use std::f32::consts::TAU;
 use std::fmt::Display;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum FourWayHat {
     #[default]
@@ -11,8 +14,230 @@ pub enum FourWayHat {
     West = 3,
 }
 
+impl FourWayHat {
+    /// Direction of the hat or `None` if centered.
+    #[profiling::function]
+    pub fn angle(&self) -> Option<HatAngle> {
+        match self {
+            FourWayHat::Centered => None,
+            FourWayHat::North => Some(HatAngle(0)),
+            FourWayHat::East => Some(HatAngle(9000)),
+            FourWayHat::South => Some(HatAngle(18000)),
+            FourWayHat::West => Some(HatAngle(27000)),
+        }
+    }
+
+    /// Nearest 4-way direction for an angle.
+    #[profiling::function]
+    pub fn nearest(angle: HatAngle) -> Self {
+        match ((angle.0 + 4500) / 9000) % 4 {
+            0 => FourWayHat::North,
+            1 => FourWayHat::East,
+            2 => FourWayHat::South,
+            _ => FourWayHat::West,
+        }
+    }
+}
+
+/// 8-way direction including diagonals for continuous hats, encoded in steps of 45° (NE = 4500, E = 9000, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EightWayHat {
+    #[default]
+    Centered,
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl EightWayHat {
+    const DIRECTIONS: [EightWayHat; 8] = [
+        EightWayHat::North,
+        EightWayHat::NorthEast,
+        EightWayHat::East,
+        EightWayHat::SouthEast,
+        EightWayHat::South,
+        EightWayHat::SouthWest,
+        EightWayHat::West,
+        EightWayHat::NorthWest,
+    ];
+
+    /// Direction of the hat or `None` if centered.
+    #[profiling::function]
+    pub fn angle(&self) -> Option<HatAngle> {
+        let index = Self::DIRECTIONS.iter().position(|d| d == self)?;
+        Some(HatAngle(index as u32 * 4500))
+    }
+
+    /// Direction of the 45° sector an angle falls into, e.g. 22.5°..67.5° is [NorthEast](Self::NorthEast).
+    #[profiling::function]
+    pub fn nearest(angle: HatAngle) -> Self {
+        Self::DIRECTIONS[(((angle.0 + 2250) / 4500) % 8) as usize]
+    }
+
+    /// Decodes a raw continuous hat value. Values outside of 0..360° decode to [Centered](Self::Centered).
+    #[profiling::function]
+    pub fn from_continuous(value: u32) -> Self {
+        match HatAngle::from_hundredths(value) {
+            Ok(angle) => Self::nearest(angle),
+            Err(_) => EightWayHat::Centered,
+        }
+    }
+
+    /// Raw continuous hat value for this direction.
+    #[profiling::function]
+    pub fn to_continuous(&self) -> u32 {
+        match self.angle() {
+            Some(angle) => angle.0,
+            None => u32::MAX,
+        }
+    }
+}
+
+impl From<EightWayHat> for HatState {
+    fn from(direction: EightWayHat) -> Self {
+        HatState::Continuous(direction.to_continuous())
+    }
+}
+
+/// Validated direction of a continuous hat switch in 1/100° clockwise from North.
+///
+/// Valid angles are in the range 0..360°, i.e. 0..=35999 hundredths of a degree.
+/// Unlike the raw value of [HatState::Continuous], a `HatAngle` can never represent the centered state (`u32::MAX`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u32", into = "u32"))]
+pub struct HatAngle(u32);
+
+impl HatAngle {
+    pub const NORTH: HatAngle = HatAngle(0);
+    pub const EAST: HatAngle = HatAngle(9000);
+    pub const SOUTH: HatAngle = HatAngle(18000);
+    pub const WEST: HatAngle = HatAngle(27000);
+
+    #[profiling::function]
+    pub fn from_hundredths(hundredths: u32) -> Result<Self, Error> {
+        if hundredths >= 36000 {
+            return Err(Error::App(AppError::InvalidHatAngle(
+                hundredths as f32 / 100.0,
+            )));
+        }
+
+        Ok(Self(hundredths))
+    }
+
+    #[profiling::function]
+    pub fn from_degrees(degrees: f32) -> Result<Self, Error> {
+        if !(0.0..360.0).contains(&degrees) {
+            return Err(Error::App(AppError::InvalidHatAngle(degrees)));
+        }
+
+        // Rounding may land on exactly 360° for values just below it
+        Ok(Self((degrees * 100.0).round() as u32 % 36000))
+    }
+
+    #[profiling::function]
+    pub fn from_radians(radians: f32) -> Result<Self, Error> {
+        if !(0.0..TAU).contains(&radians) {
+            return Err(Error::App(AppError::InvalidHatAngle(radians.to_degrees())));
+        }
+
+        Self::from_degrees(radians.to_degrees().min(359.99))
+    }
+
+    /// Direction of the vector (x, y), with x pointing right (East) and y pointing up (North).
+    ///
+    /// Returns `None` for a zero-length or non-finite vector.
+    #[profiling::function]
+    pub fn from_vector(x: f32, y: f32) -> Option<Self> {
+        if !x.is_finite() || !y.is_finite() || (x == 0.0 && y == 0.0) {
+            return None;
+        }
+
+        let radians = x.atan2(y).rem_euclid(TAU);
+        Some(Self((radians.to_degrees() * 100.0).round() as u32 % 36000))
+    }
+
+    #[profiling::function]
+    pub fn hundredths(&self) -> u32 {
+        self.0
+    }
+
+    #[profiling::function]
+    pub fn degrees(&self) -> f32 {
+        self.0 as f32 / 100.0
+    }
+
+    #[profiling::function]
+    pub fn radians(&self) -> f32 {
+        self.degrees().to_radians()
+    }
+}
+
+impl Display for HatAngle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:.2}°", self.degrees()))
+    }
+}
+
+impl TryFrom<u32> for HatAngle {
+    type Error = Error;
+
+    fn try_from(hundredths: u32) -> Result<Self, Self::Error> {
+        Self::from_hundredths(hundredths)
+    }
+}
+
+impl From<HatAngle> for u32 {
+    fn from(angle: HatAngle) -> Self {
+        angle.0
+    }
+}
+
+impl From<HatAngle> for HatState {
+    fn from(angle: HatAngle) -> Self {
+        HatState::Continuous(angle.0)
+    }
+}
+
+/// Configured type of a hat switch: a 4-way discrete switch or a continuous 360° switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HatType {
+    #[default]
+    Discrete,
+    Continuous,
+}
+
+impl Display for HatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match *self {
+            HatType::Discrete => "discrete",
+            HatType::Continuous => "continuous",
+        };
+        f.write_fmt(format_args!("{}", str))
+    }
+}
+
+impl HatType {
+    /// Centered state for this hat type.
+    #[profiling::function]
+    pub fn centered(&self) -> HatState {
+        match self {
+            HatType::Discrete => HatState::Discrete(FourWayHat::Centered),
+            HatType::Continuous => HatState::Continuous(u32::MAX),
+        }
+    }
+}
+
 /// Common state for either a 4-way hat or a continuous 360° hat switch
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HatState {
     Discrete(FourWayHat),
     Continuous(u32),
@@ -34,21 +259,40 @@ impl Default for HatState {
 }
 
 impl HatState {
+    #[profiling::function]
+    pub fn hat_type(&self) -> HatType {
+        match self {
+            HatState::Discrete(_) => HatType::Discrete,
+            HatState::Continuous(_) => HatType::Continuous,
+        }
+    }
+
+    /// Direction of the hat or `None` if centered.
+    ///
+    /// Continuous values outside of 0..360° are treated as centered.
+    #[profiling::function]
+    pub fn angle(&self) -> Option<HatAngle> {
+        match self {
+            HatState::Discrete(d) => d.angle(),
+            HatState::Continuous(c) => HatAngle::from_hundredths(*c).ok(),
+        }
+    }
+
     #[profiling::function]
     fn reset(&mut self) {
-        *self = match self {
-            HatState::Discrete(_) => HatState::Discrete(FourWayHat::Centered),
-            HatState::Continuous(_) => HatState::Continuous(u32::MAX),
-        };
+        *self = self.hat_type().centered();
     }
 }
 
 /// Current state of an enabled device hat switch.
 ///
 /// A vJoy hat switch is either a 4-way discrete switch or a continuous switch. Continuous switches feature a range of 360° with a 1/100° resolution.
+/// The type is configured by the driver and fixed per hat, see [hat_type](Self::hat_type).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hat {
     pub(crate) id: u8,
+    pub(crate) hat_type: HatType,
     pub(crate) state: HatState,
 }
 
@@ -65,6 +309,37 @@ impl Hat {
         self.state
     }
 
+    #[profiling::function]
+    pub fn hat_type(&self) -> HatType {
+        self.hat_type
+    }
+
+    /// Current state as an 8-way direction or `None` for discrete hats.
+    #[profiling::function]
+    pub fn eight_way(&self) -> Option<EightWayHat> {
+        match (self.hat_type, self.state) {
+            (HatType::Continuous, HatState::Continuous(c)) => Some(EightWayHat::from_continuous(c)),
+            _ => None,
+        }
+    }
+
+    /// Sets the hat to an 8-way direction.
+    ///
+    /// Discrete hats are set to the [nearest](FourWayHat::nearest) 4-way direction, diagonals snap clockwise, e.g. NorthEast to East.
+    #[profiling::function]
+    pub fn set_eight_way(&mut self, direction: EightWayHat) {
+        let Some(angle) = direction.angle() else {
+            self.reset();
+            return;
+        };
+
+        self.state = match self.hat_type {
+            HatType::Discrete => HatState::Discrete(FourWayHat::nearest(angle)),
+            HatType::Continuous => HatState::from(direction),
+        };
+    }
+
+    /// Sets the state without validation. Prefer [Device::set_hat](crate::Device::set_hat), which rejects states that don't match the [hat_type](Self::hat_type).
     #[profiling::function]
     pub fn set(&mut self, state: HatState) {
         self.state = state;
@@ -72,6 +347,35 @@ impl Hat {
 
     #[profiling::function]
     pub fn reset(&mut self) {
-        self.state.reset();
+        self.state = self.hat_type.centered();
+    }
+
+    /// Sets the hat from an analog vector, e.g. a thumbstick or touch pad, with x pointing right (East) and y pointing up (North).
+    ///
+    /// Vectors with a length below `deadzone` center the hat.
+    /// Discrete hats are set to the nearest [FourWayHat], continuous hats to the exact angle of the vector.
+    ///
+    /// Like [set](Self::set), this only marks the device dirty if the hat was borrowed via [Device::hats_mut](crate::Device::hats_mut).
+    /// Prefer [Device::set_hat_from_vector](crate::Device::set_hat_from_vector).
+    #[profiling::function]
+    pub fn set_from_vector(&mut self, x: f32, y: f32, deadzone: f32) {
+        self.state = self.state_from_vector(x, y, deadzone);
+    }
+
+    pub(crate) fn state_from_vector(&self, x: f32, y: f32, deadzone: f32) -> HatState {
+        let angle = if x.hypot(y) >= deadzone {
+            HatAngle::from_vector(x, y)
+        } else {
+            None
+        };
+
+        let Some(angle) = angle else {
+            return self.hat_type.centered();
+        };
+
+        match self.hat_type {
+            HatType::Discrete => HatState::Discrete(FourWayHat::nearest(angle)),
+            HatType::Continuous => HatState::from(angle),
+        }
     }
 }
@@ -1,19 +1,56 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(target_os = "windows")]
 mod vjoy;
-pub use crate::vjoy::VJoy;
+#[cfg(target_os = "windows")]
+pub use crate::vjoy::{DEFAULT_DLL_LOCATION, VJoy, VJoyOptions};
 
 mod error;
 pub use error::*;
 
 mod device;
-pub use device::Device;
+pub use device::{Device, DeviceBuilder, DeviceDiff};
 
 mod axis;
-pub use axis::Axis;
+pub use axis::{Axis, AxisKind};
 
 mod hat;
-pub use hat::{FourWayHat, Hat, HatState};
+pub use hat::{EightWayHat, FourWayHat, Hat, HatAngle, HatState, HatType};
 
 mod button;
 pub use button::{Button, ButtonState};
+
+mod gesture;
+pub use gesture::{Gesture, GestureClassifier, GestureConfig, GestureEvent, GestureMapping};
+
+mod dpad;
+pub use dpad::{DPad, DPadButtons, SocdPolicy};
+
+mod capabilities;
+pub use capabilities::DeviceCapabilities;
+
+mod query;
+pub use query::{DeviceMatch, DeviceQuery, DeviceStatus};
+
+mod alias;
+pub use alias::{DeviceAlias, DeviceAliases};
+
+mod crossfade;
+pub use crossfade::{Crossfade, CrossfadeScheduler};
+
+mod merge;
+pub use merge::{AxisMerge, ButtonMerge, HatMerge, MergePolicy, MergeSource};
+
+mod recording;
+pub use recording::{RECORDING_HEADER, RecordedState, Recorder, Recording};
+
+mod replay;
+pub use replay::{DeviceSink, MemorySink, Replayer};
+
+mod timeline;
+
+mod snapshot;
+pub use snapshot::VJoySnapshot;
+
+pub mod ffb;
+pub mod script;
@@ -0,0 +1,82 @@
+use crate::device::Device;
+use std::time::Duration;
+
+/// One input to [Device::merge], e.g. the state produced by a HOTAS, pedals, a keyboard or a network client.
+///
+/// The source device needs the layout of the merged device, but not its device ID.
+/// `priority` decides for the priority policies, higher wins. `updated` is the time the source last changed, used by [HatMerge::MostRecent].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeSource<'a> {
+    pub device: &'a Device,
+    pub priority: i32,
+    pub updated: Duration,
+}
+
+impl<'a> MergeSource<'a> {
+    /// Source with priority 0, updated at time 0.
+    #[profiling::function]
+    pub fn new(device: &'a Device) -> Self {
+        Self {
+            device,
+            priority: 0,
+            updated: Duration::ZERO,
+        }
+    }
+
+    #[profiling::function]
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    #[profiling::function]
+    pub fn updated(mut self, updated: Duration) -> Self {
+        self.updated = updated;
+        self
+    }
+}
+
+/// How buttons of multiple sources are combined.
+///
+/// - `Or`: pressed if pressed in any source.
+/// - `Priority`: state of the source with the highest priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ButtonMerge {
+    #[default]
+    Or,
+    Priority,
+}
+
+/// How axes of multiple sources are combined. Deflection is measured from [Axis::CENTER](crate::Axis::CENTER).
+///
+/// - `MaxDeflection`: value of the source that is deflected the most.
+/// - `SumClamp`: sum of all deflections, clamped to the axis range.
+/// - `Average`: mean of all values.
+/// - `Priority`: value of the source with the highest priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AxisMerge {
+    #[default]
+    MaxDeflection,
+    SumClamp,
+    Average,
+    Priority,
+}
+
+/// How hats of multiple sources are combined. Centered hats are ignored, so a hat is only centered if it is centered in all sources.
+///
+/// - `Priority`: direction of the source with the highest priority.
+/// - `MostRecent`: direction of the most recently updated source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HatMerge {
+    #[default]
+    Priority,
+    MostRecent,
+}
+
+/// Policies per component type for [Device::merge]. Ties are resolved in favor of the earlier source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MergePolicy {
+    pub buttons: ButtonMerge,
+    pub axes: AxisMerge,
+    pub hats: HatMerge,
+}
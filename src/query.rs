@@ -0,0 +1,275 @@
+use crate::axis::AxisKind;
+use crate::capabilities::DeviceCapabilities;
+use crate::device::DeviceBuilder;
+use crate::hat::HatType;
+use std::fmt::Display;
+use std::ops::{Bound, RangeBounds};
+#[cfg(target_os = "windows")]
+use vjoy_sys::VjdStat;
+
+/// Ownership of a vJoy device as reported by the driver.
+///
+/// - `Owned`: acquired by this process.
+/// - `Free`: configured and not acquired by any feeder.
+/// - `Busy`: acquired by another feeder.
+/// - `Missing`: not configured or disabled in vJoyConf.
+/// - `Unknown`: the driver reported an unknown status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceStatus {
+    Owned,
+    Free,
+    Busy,
+    Missing,
+    Unknown,
+}
+
+#[cfg(target_os = "windows")]
+impl From<VjdStat> for DeviceStatus {
+    #[profiling::function]
+    fn from(status: VjdStat) -> Self {
+        match status {
+            vjoy_sys::VjdStat_VJD_STAT_OWN => DeviceStatus::Owned,
+            vjoy_sys::VjdStat_VJD_STAT_FREE => DeviceStatus::Free,
+            vjoy_sys::VjdStat_VJD_STAT_BUSY => DeviceStatus::Busy,
+            vjoy_sys::VjdStat_VJD_STAT_MISS => DeviceStatus::Missing,
+            _ => DeviceStatus::Unknown,
+        }
+    }
+}
+
+impl Display for DeviceStatus {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DeviceStatus::Owned => "owned",
+            DeviceStatus::Free => "free",
+            DeviceStatus::Busy => "busy",
+            DeviceStatus::Missing => "missing",
+            DeviceStatus::Unknown => "unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Requirements for selecting a vJoy device by its layout rather than its ID.
+///
+/// All vJoy devices share the same GUID, so applications that expect e.g. a wheel-shaped device
+/// can describe it and let [VJoy::find_devices](crate::VJoy::find_devices) pick among the configured devices.
+/// Without further requirements, a query matches every owned or free device.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, AxisKind, DeviceQuery, Error};
+/// # let vjoy = VJoy::from_default_dll_location()?;
+/// let wheel = DeviceQuery::new()
+///     .axes(&[AxisKind::X, AxisKind::Z, AxisKind::Rz])
+///     .buttons(16..=32);
+/// let device_id = vjoy.find_device(&wheel)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceQuery {
+    pub(crate) axes: Vec<AxisKind>,
+    pub(crate) min_buttons: u32,
+    pub(crate) max_buttons: u32,
+    pub(crate) hat_type: Option<HatType>,
+    pub(crate) min_hats: u32,
+    pub(crate) max_hats: u32,
+    pub(crate) statuses: Vec<DeviceStatus>,
+}
+
+impl Default for DeviceQuery {
+    fn default() -> Self {
+        Self {
+            axes: Vec::new(),
+            min_buttons: 0,
+            max_buttons: DeviceBuilder::MAX_BUTTONS,
+            hat_type: None,
+            min_hats: 0,
+            max_hats: DeviceBuilder::MAX_HATS as u32,
+            statuses: Vec::new(),
+        }
+    }
+}
+
+impl DeviceQuery {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires the axis.
+    #[profiling::function]
+    pub fn axis(mut self, kind: AxisKind) -> Self {
+        if !self.axes.contains(&kind) {
+            self.axes.push(kind);
+            self.axes.sort_unstable();
+        }
+        self
+    }
+
+    /// Requires all axes.
+    #[profiling::function]
+    pub fn axes(self, kinds: &[AxisKind]) -> Self {
+        kinds.iter().fold(self, |query, kind| query.axis(*kind))
+    }
+
+    /// Requires a button count within the range, e.g. `8..` or `16..=32`.
+    #[profiling::function]
+    pub fn buttons<R: RangeBounds<u32>>(mut self, range: R) -> Self {
+        (self.min_buttons, self.max_buttons) = Self::bounds(range, DeviceBuilder::MAX_BUTTONS);
+        self
+    }
+
+    /// Requires at least one hat, with all hats of the type.
+    #[profiling::function]
+    pub fn hat_type(mut self, hat_type: HatType) -> Self {
+        self.hat_type = Some(hat_type);
+        self
+    }
+
+    /// Requires a hat count within the range.
+    #[profiling::function]
+    pub fn hats<R: RangeBounds<u32>>(mut self, range: R) -> Self {
+        (self.min_hats, self.max_hats) = Self::bounds(range, DeviceBuilder::MAX_HATS as u32);
+        self
+    }
+
+    /// Allows devices with the status. Multiple calls allow any of the given statuses.
+    ///
+    /// Without this, owned and free devices match.
+    #[profiling::function]
+    pub fn status(mut self, status: DeviceStatus) -> Self {
+        if !self.statuses.contains(&status) {
+            self.statuses.push(status);
+        }
+        self
+    }
+
+    #[profiling::function]
+    pub fn allows_status(&self, status: DeviceStatus) -> bool {
+        if self.statuses.is_empty() {
+            matches!(status, DeviceStatus::Owned | DeviceStatus::Free)
+        } else {
+            self.statuses.contains(&status)
+        }
+    }
+
+    #[profiling::function]
+    pub fn matches(&self, capabilities: &DeviceCapabilities, status: DeviceStatus) -> bool {
+        let num_hats = capabilities.num_hats() as u32;
+
+        self.allows_status(status)
+            && (self.min_buttons..=self.max_buttons).contains(&capabilities.num_buttons())
+            && self.axes.iter().all(|kind| capabilities.has_axis(*kind))
+            && (self.min_hats..=self.max_hats).contains(&num_hats)
+            && self.hat_type.is_none_or(|hat_type| {
+                num_hats > 0 && capabilities.hats().iter().all(|hat| *hat == hat_type)
+            })
+    }
+
+    /// All matching candidates, best match first.
+    ///
+    /// Devices are ranked by their closest fit: fewest unused axes, then fewest hats and buttons beyond the required minimum.
+    /// Ties are broken by status (owned before free) and device ID.
+    #[profiling::function]
+    pub fn rank<I>(&self, candidates: I) -> Vec<DeviceMatch>
+    where
+        I: IntoIterator<Item = (DeviceCapabilities, DeviceStatus)>,
+    {
+        let mut matches: Vec<DeviceMatch> = candidates
+            .into_iter()
+            .filter(|(capabilities, status)| self.matches(capabilities, *status))
+            .map(|(capabilities, status)| DeviceMatch {
+                capabilities,
+                status,
+            })
+            .collect();
+
+        matches.sort_by_key(|candidate| {
+            let capabilities = &candidate.capabilities;
+            (
+                capabilities.num_axes() - self.axes.len(),
+                capabilities.num_hats() as u32 - self.min_hats,
+                capabilities.num_buttons() - self.min_buttons,
+                candidate.status,
+                capabilities.device_id(),
+            )
+        });
+
+        matches
+    }
+
+    fn bounds<R: RangeBounds<u32>>(range: R, max: u32) -> (u32, u32) {
+        let min = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(end) => *end,
+            Bound::Excluded(end) => end.saturating_sub(1),
+            Bound::Unbounded => max,
+        };
+
+        (min, max)
+    }
+}
+
+impl Display for DeviceQuery {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let axes: Vec<&str> = self.axes.iter().map(|kind| kind.display_name()).collect();
+        f.write_fmt(format_args!(
+            "buttons: {}..={} | axes: [{}] | hats: {}..={}",
+            self.min_buttons,
+            self.max_buttons,
+            axes.join(", "),
+            self.min_hats,
+            self.max_hats
+        ))?;
+        if let Some(hat_type) = self.hat_type {
+            f.write_fmt(format_args!(" {}", hat_type))?;
+        }
+        if !self.statuses.is_empty() {
+            let statuses: Vec<String> = self.statuses.iter().map(|s| s.to_string()).collect();
+            f.write_fmt(format_args!(" | status: [{}]", statuses.join(", ")))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A device that matched a [DeviceQuery].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceMatch {
+    pub(crate) capabilities: DeviceCapabilities,
+    pub(crate) status: DeviceStatus,
+}
+
+impl DeviceMatch {
+    #[profiling::function]
+    pub fn device_id(&self) -> u32 {
+        self.capabilities.device_id()
+    }
+
+    #[profiling::function]
+    pub fn capabilities(&self) -> &DeviceCapabilities {
+        &self.capabilities
+    }
+
+    #[profiling::function]
+    pub fn status(&self) -> DeviceStatus {
+        self.status
+    }
+}
+
+impl Display for DeviceMatch {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} | status: {}",
+            self.capabilities, self.status
+        ))
+    }
+}
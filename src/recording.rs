@@ -0,0 +1,435 @@
+use crate::axis::{Axis, AxisKind};
+use crate::button::ButtonState;
+use crate::device::{Device, DeviceBuilder};
+use crate::error::{AppError, Error};
+use crate::hat::{FourWayHat, HatState};
+use crate::replay::Replayer;
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// First line of every device recording file.
+pub const RECORDING_HEADER: &str = "# vjoy recording v1";
+
+/// A device state with the time it was committed, relative to the start of the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedState {
+    pub timestamp: Duration,
+    pub device: Device,
+}
+
+impl RecordedState {
+    #[profiling::function]
+    pub(crate) fn to_line(&self) -> String {
+        let device = &self.device;
+        let buttons: String = device
+            .buttons
+            .iter()
+            .map(|button| match button.state {
+                ButtonState::Released => '0',
+                ButtonState::Pressed => '1',
+            })
+            .collect();
+        let axes: Vec<String> = device
+            .axes
+            .iter()
+            .map(|axis| match axis.kind() {
+                Some(kind) => format!("{}={}", kind.display_name(), axis.value),
+                None => format!("{:#x}={}", axis.hid_usage, axis.value),
+            })
+            .collect();
+        let hats: Vec<String> = device
+            .hats
+            .iter()
+            .map(|hat| match hat.state {
+                HatState::Discrete(direction) => {
+                    let direction = match direction {
+                        FourWayHat::Centered => "C",
+                        FourWayHat::North => "N",
+                        FourWayHat::East => "E",
+                        FourWayHat::South => "S",
+                        FourWayHat::West => "W",
+                    };
+                    format!("d:{}", direction)
+                }
+                HatState::Continuous(value) if value >= 36000 => "c:-".to_string(),
+                HatState::Continuous(value) => format!("c:{}", value),
+            })
+            .collect();
+
+        format!(
+            "{} {} {} {} {}",
+            self.timestamp.as_micros(),
+            device.id,
+            Self::field(buttons),
+            Self::field(axes.join(",")),
+            Self::field(hats.join(","))
+        )
+    }
+
+    #[profiling::function]
+    pub(crate) fn from_line(line_number: usize, line: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| {
+            Error::App(AppError::InvalidRecordingLine(
+                line_number,
+                reason.to_string(),
+            ))
+        };
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [timestamp, device_id, buttons, axes, hats] = fields[..] else {
+            return Err(invalid(
+                "expected timestamp, device ID, buttons, axes and hats",
+            ));
+        };
+
+        let timestamp = timestamp
+            .parse::<u64>()
+            .map_err(|_| invalid("invalid timestamp"))?;
+        let device_id = device_id
+            .parse::<u32>()
+            .map_err(|_| invalid("invalid device ID"))?;
+
+        let buttons = Self::values(buttons)
+            .flat_map(str::chars)
+            .map(|state| match state {
+                '0' => Ok(ButtonState::Released),
+                '1' => Ok(ButtonState::Pressed),
+                _ => Err(invalid("invalid button state")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let axes = Self::values(axes)
+            .flat_map(|axes| axes.split(','))
+            .map(|axis| {
+                let (name, value) = axis
+                    .split_once('=')
+                    .ok_or_else(|| invalid("invalid axis"))?;
+                let usage = match name.strip_prefix("0x") {
+                    Some(hid_usage) => u32::from_str_radix(hid_usage, 16)
+                        .map_err(|_| invalid("invalid axis HID usage"))?,
+                    None => AxisKind::ALL
+                        .into_iter()
+                        .find(|kind| kind.display_name() == name)
+                        .ok_or_else(|| invalid("unknown axis"))?
+                        .hid_usage(),
+                };
+                let value = value
+                    .parse::<i32>()
+                    .map_err(|_| invalid("invalid axis value"))?;
+                Ok((usage, value))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut known_axes = Vec::new();
+        let mut unknown_axes: Vec<(u32, i32)> = Vec::new();
+        for (hid_usage, value) in axes {
+            match AxisKind::from_hid_usage(hid_usage) {
+                Some(kind) => known_axes.push((kind, value)),
+                None if unknown_axes.iter().any(|(usage, _)| *usage == hid_usage) => {
+                    return Err(invalid("duplicate axis"));
+                }
+                None => unknown_axes.push((hid_usage, value)),
+            }
+        }
+        let axes = known_axes;
+
+        let hats = Self::values(hats)
+            .flat_map(|hats| hats.split(','))
+            .map(|hat| match hat.split_once(':') {
+                Some(("d", direction)) => match direction {
+                    "C" => Ok(HatState::Discrete(FourWayHat::Centered)),
+                    "N" => Ok(HatState::Discrete(FourWayHat::North)),
+                    "E" => Ok(HatState::Discrete(FourWayHat::East)),
+                    "S" => Ok(HatState::Discrete(FourWayHat::South)),
+                    "W" => Ok(HatState::Discrete(FourWayHat::West)),
+                    _ => Err(invalid("invalid discrete hat direction")),
+                },
+                Some(("c", "-")) => Ok(HatState::Continuous(u32::MAX)),
+                Some(("c", value)) => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|value| *value < 36000)
+                    .map(HatState::Continuous)
+                    .ok_or_else(|| invalid("invalid continuous hat angle")),
+                _ => Err(invalid("invalid hat")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut device = DeviceBuilder::new(device_id)
+            .buttons(buttons.len() as u32)
+            .axes(&axes.iter().map(|(kind, _)| *kind).collect::<Vec<_>>());
+        for hat in &hats {
+            device = device.hat(hat.hat_type());
+        }
+        let mut device = device.build().map_err(|e| invalid(&e.to_string()))?;
+        if device.axes.len() != axes.len() {
+            return Err(invalid("duplicate axis"));
+        }
+
+        for (button, state) in device.buttons.iter_mut().zip(buttons) {
+            button.state = state;
+        }
+        for (axis, (_, value)) in device.axes.iter_mut().zip(Self::sorted(axes)) {
+            axis.value = value;
+        }
+        // Axes with an unknown HID usage can't be built, so they are appended as is
+        for (hid_usage, value) in unknown_axes {
+            device.axes.push(Axis {
+                id: device.axes.len() as u32 + 1,
+                value,
+                display_name: String::new(),
+                hid_usage,
+            });
+        }
+        for (hat, state) in device.hats.iter_mut().zip(hats) {
+            hat.state = state;
+        }
+
+        Ok(Self {
+            timestamp: Duration::from_micros(timestamp),
+            device,
+        })
+    }
+
+    /// Components are separated by commas, `-` marks a device without components of that type.
+    fn field(values: String) -> String {
+        if values.is_empty() {
+            "-".to_string()
+        } else {
+            values
+        }
+    }
+
+    fn values(field: &str) -> impl Iterator<Item = &str> {
+        (field != "-").then_some(field).into_iter()
+    }
+
+    fn sorted(mut axes: Vec<(AxisKind, i32)>) -> Vec<(AxisKind, i32)> {
+        axes.sort_unstable_by_key(|(kind, _)| *kind);
+        axes
+    }
+}
+
+/// A recorded sequence of committed device states.
+///
+/// Recordings are stored as text: a [header](RECORDING_HEADER) line followed by one state per line with the timestamp in microseconds,
+/// the device ID, the buttons as `0`/`1` by ID, the axes as `name=value` and the hats as `d:N|E|S|W|C` or `c:<1/100°>|-`,
+/// e.g. `1500 1 0100 X=16384,Y=0 d:N`. Devices without buttons, axes or hats have a `-` in that field.
+/// Axes with an unknown HID usage are named by the usage in hex, e.g. `0x40=100`, and are read back after the known axes.
+/// The header is required. Empty lines and other lines starting with `#` are ignored.
+///
+/// For analysis in spreadsheets and notebooks, recordings can also be imported and exported as timelines in CSV and JSON lines, see [read_csv](Self::read_csv).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Recording {
+    pub states: Vec<RecordedState>,
+}
+
+impl Recording {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[profiling::function]
+    pub fn push(&mut self, timestamp: Duration, device: Device) {
+        self.states.push(RecordedState { timestamp, device });
+    }
+
+    /// Time of the last state.
+    #[profiling::function]
+    pub fn duration(&self) -> Duration {
+        self.states
+            .last()
+            .map_or(Duration::ZERO, |state| state.timestamp)
+    }
+
+    #[profiling::function]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+        Self::read_from(BufReader::new(file))
+    }
+
+    #[profiling::function]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        self.write_to(LineWriter::new(file))
+    }
+
+    #[profiling::function]
+    pub fn read_from<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut recording = Self::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            let line = line.trim();
+            if index == 0 {
+                if line != RECORDING_HEADER {
+                    return Err(Error::App(AppError::InvalidRecordingLine(
+                        1,
+                        format!("expected header \"{}\"", RECORDING_HEADER),
+                    )));
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            recording
+                .states
+                .push(RecordedState::from_line(index + 1, line)?);
+        }
+
+        Ok(recording)
+    }
+
+    #[profiling::function]
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        writeln!(writer, "{}", RECORDING_HEADER).map_err(Error::Io)?;
+        for state in &self.states {
+            writeln!(writer, "{}", state.to_line()).map_err(Error::Io)?;
+        }
+
+        writer.flush().map_err(Error::Io)
+    }
+
+    #[profiling::function]
+    pub fn replayer(&self) -> Replayer<'_> {
+        Replayer::new(self)
+    }
+}
+
+/// Writes committed device states to a recording file while they are sent, e.g. to reproduce reports of unexpected inputs.
+///
+/// Timestamps are measured from the creation of the recorder with a monotonic clock.
+/// Use [VJoy::start_recording](crate::VJoy::start_recording) to record every state sent to the driver.
+///
+/// With [rotate](Self::rotate), a file that reaches the size limit is renamed to `<path>.1`, older files move to `<path>.2` and so on,
+/// and recording continues in a new file at `path`. Files beyond the kept count are deleted,
+/// so the recording takes at most about `max_file_size * (max_files + 1)` bytes.
+///
+/// ```no_run
+/// # use vjoy::{Error, Recorder};
+/// let recorder = Recorder::create("flight.rec")?.rotate(10 * 1024 * 1024, 4);
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    writer: LineWriter<File>,
+    start: Instant,
+    file_size: u64,
+    max_file_size: Option<u64>,
+    max_files: usize,
+}
+
+impl Recorder {
+    /// Creates or truncates the recording file at `path`.
+    #[profiling::function]
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let (writer, file_size) = Self::open(&path)?;
+
+        Ok(Self {
+            path,
+            writer,
+            start: Instant::now(),
+            file_size,
+            max_file_size: None,
+            max_files: 0,
+        })
+    }
+
+    /// Starts a new file once the current one would exceed `max_file_size` bytes, keeping `max_files` previous files.
+    #[profiling::function]
+    pub fn rotate(mut self, max_file_size: u64, max_files: usize) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self.max_files = max_files;
+        self
+    }
+
+    #[profiling::function]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the current file in bytes.
+    #[profiling::function]
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+
+    /// Path of the `index`-th previous file, e.g. `flight.rec.1` for index 1.
+    #[profiling::function]
+    pub fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    /// Records a device state with the time elapsed since the recorder was created.
+    #[profiling::function]
+    pub fn record(&mut self, device: &Device) -> Result<(), Error> {
+        let timestamp = self.start.elapsed();
+        self.record_at(timestamp, device)
+    }
+
+    #[profiling::function]
+    pub fn record_at(&mut self, timestamp: Duration, device: &Device) -> Result<(), Error> {
+        let recorded = RecordedState {
+            timestamp,
+            device: device.clone(),
+        };
+        let line = recorded.to_line();
+        let line_size = line.len() as u64 + 1;
+
+        if let Some(max_file_size) = self.max_file_size {
+            let header_size = RECORDING_HEADER.len() as u64 + 1;
+            if self.file_size > header_size && self.file_size + line_size > max_file_size {
+                self.rotate_files()?;
+            }
+        }
+
+        writeln!(self.writer, "{}", line).map_err(Error::Io)?;
+        self.file_size += line_size;
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(Error::Io)
+    }
+
+    #[profiling::function]
+    fn rotate_files(&mut self) -> Result<(), Error> {
+        self.flush()?;
+
+        if self.max_files > 0 {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1)).map_err(Error::Io)?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1)).map_err(Error::Io)?;
+        }
+
+        (self.writer, self.file_size) = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    fn open(path: &Path) -> Result<(LineWriter<File>, u64), Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(Error::Io)?;
+        let mut writer = LineWriter::new(file);
+        writeln!(writer, "{}", RECORDING_HEADER).map_err(Error::Io)?;
+
+        Ok((writer, RECORDING_HEADER.len() as u64 + 1))
+    }
+}
@@ -0,0 +1,263 @@
+use crate::device::Device;
+use crate::error::Error;
+use crate::recording::{RecordedState, Recording};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Output for device states, e.g. the vJoy driver or an in-memory backend for tests.
+///
+/// Implemented for [VJoy](crate::VJoy), [MemorySink] and closures taking a [Device].
+pub trait DeviceSink {
+    fn send(&mut self, device: &Device) -> Result<(), Error>;
+}
+
+impl<F: FnMut(&Device) -> Result<(), Error>> DeviceSink for F {
+    fn send(&mut self, device: &Device) -> Result<(), Error> {
+        self(device)
+    }
+}
+
+/// Keeps the last state per device and every sent state in memory, e.g. to run replays in automated tests without the driver.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MemorySink {
+    devices: BTreeMap<u32, Device>,
+    sent: Vec<Device>,
+}
+
+impl MemorySink {
+    #[profiling::function]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Last state sent for the device.
+    #[profiling::function]
+    pub fn device(&self, device_id: u32) -> Option<&Device> {
+        self.devices.get(&device_id)
+    }
+
+    /// Last states of all devices, ordered by device ID.
+    #[profiling::function]
+    pub fn devices(&self) -> impl Iterator<Item = &Device> {
+        self.devices.values()
+    }
+
+    /// All sent states in order.
+    #[profiling::function]
+    pub fn sent(&self) -> &[Device] {
+        &self.sent
+    }
+
+    #[profiling::function]
+    pub fn clear(&mut self) {
+        self.devices.clear();
+        self.sent.clear();
+    }
+}
+
+impl DeviceSink for MemorySink {
+    #[profiling::function]
+    fn send(&mut self, device: &Device) -> Result<(), Error> {
+        let mut device = device.clone();
+        device.dirty = false;
+        self.devices.insert(device.id, device.clone());
+        self.sent.push(device);
+
+        Ok(())
+    }
+}
+
+/// Plays a [Recording] back into a [DeviceSink] in recorded order and timing.
+///
+/// Drive the replay either via [advance](Self::advance) with the elapsed time, e.g. once per frame or in fixed steps in tests,
+/// or in real time via [play](Self::play). Playback time is scaled by [speed](Self::speed).
+///
+/// ```no_run
+/// # use vjoy::{VJoy, Error, Recording};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// let recording = Recording::load("approach.rec")?;
+/// recording.replayer().speed(2.0).remap(1, 3).play(&mut vjoy)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Replayer<'a> {
+    states: &'a [RecordedState],
+    position: usize,
+    time: Duration,
+    speed: f64,
+    looping: bool,
+    remap: BTreeMap<u32, u32>,
+    catch_up: bool,
+}
+
+impl<'a> Replayer<'a> {
+    #[profiling::function]
+    pub fn new(recording: &'a Recording) -> Self {
+        Self {
+            states: &recording.states,
+            position: 0,
+            time: Duration::ZERO,
+            speed: 1.0,
+            looping: false,
+            remap: BTreeMap::new(),
+            catch_up: false,
+        }
+    }
+
+    /// Playback speed factor, e.g. 2.0 for double speed. Negative values are treated as 0.0, which pauses the replay.
+    #[profiling::function]
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.set_speed(speed);
+        self
+    }
+
+    #[profiling::function]
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Restarts from the beginning after the last state. The loop length is the timestamp of the last state.
+    ///
+    /// Empty recordings and recordings whose states are all at 0 are played once.
+    #[profiling::function]
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Sends states recorded for device `from` to device `to`. Other devices keep their IDs.
+    #[profiling::function]
+    pub fn remap(mut self, from: u32, to: u32) -> Self {
+        self.remap.insert(from, to);
+        self
+    }
+
+    /// Current position in recording time.
+    #[profiling::function]
+    pub fn position(&self) -> Duration {
+        self.time
+    }
+
+    /// Timestamp of the last state.
+    #[profiling::function]
+    pub fn duration(&self) -> Duration {
+        self.states
+            .last()
+            .map_or(Duration::ZERO, |state| state.timestamp)
+    }
+
+    /// Timestamp of the next state or `None` if all states of the current pass were sent.
+    #[profiling::function]
+    pub fn next_timestamp(&self) -> Option<Duration> {
+        self.states.get(self.position).map(|state| state.timestamp)
+    }
+
+    /// Whether all states were sent. Looping replays only finish if the recording has no length to loop over.
+    #[profiling::function]
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.states.len() && (!self.looping || self.duration().is_zero())
+    }
+
+    /// Jumps to a position in recording time.
+    ///
+    /// The next [advance](Self::advance) first sends the last state of each device recorded up to that position,
+    /// so all devices reflect the recording at the new position.
+    #[profiling::function]
+    pub fn seek(&mut self, position: Duration) {
+        self.time = position.min(self.duration());
+        self.position = self
+            .states
+            .partition_point(|state| state.timestamp <= self.time);
+        self.catch_up = true;
+    }
+
+    #[profiling::function]
+    pub fn rewind(&mut self) {
+        self.seek(Duration::ZERO);
+    }
+
+    /// Advances the replay by `elapsed` playback time and sends all states that became due, each remapped.
+    ///
+    /// Returns the number of sent states. If the sink fails, the error is returned and the replay continues after that state on the next call.
+    #[profiling::function]
+    pub fn advance<S: DeviceSink + ?Sized>(
+        &mut self,
+        sink: &mut S,
+        elapsed: Duration,
+    ) -> Result<usize, Error> {
+        let mut sent = 0;
+        if self.catch_up {
+            self.catch_up = false;
+            let mut latest: BTreeMap<u32, &Device> = BTreeMap::new();
+            for state in &self.states[..self.position] {
+                latest.insert(state.device.id, &state.device);
+            }
+            for device in latest.into_values() {
+                self.send(sink, device)?;
+                sent += 1;
+            }
+        }
+
+        self.time += elapsed.mul_f64(self.speed);
+        loop {
+            while let Some(state) = self.states.get(self.position) {
+                if state.timestamp > self.time {
+                    break;
+                }
+
+                self.position += 1;
+                self.send(sink, &state.device)?;
+                sent += 1;
+            }
+
+            let duration = self.duration();
+            if !self.looping || duration.is_zero() || self.time < duration {
+                break;
+            }
+            self.time -= duration;
+            self.position = 0;
+        }
+
+        Ok(sent)
+    }
+
+    /// Plays the remaining states in real time, blocking until the replay is finished.
+    ///
+    /// A looping replay only finishes if the recording has no length, a paused replay returns immediately.
+    #[profiling::function]
+    pub fn play<S: DeviceSink + ?Sized>(&mut self, sink: &mut S) -> Result<(), Error> {
+        if self.speed == 0.0 {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let mut played = Duration::ZERO;
+
+        while !self.is_finished() {
+            if let Some(next) = self.next_timestamp() {
+                // Deadlines are measured from the start to avoid drift over long replays
+                let due = played + next.saturating_sub(self.time).div_f64(self.speed);
+                if let Some(wait) = due.checked_sub(start.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+
+            let now = start.elapsed();
+            self.advance(sink, now - played)?;
+            played = now;
+        }
+
+        Ok(())
+    }
+
+    fn send<S: DeviceSink + ?Sized>(&self, sink: &mut S, device: &Device) -> Result<(), Error> {
+        match self.remap.get(&device.id) {
+            Some(device_id) => {
+                let mut device = device.clone();
+                device.id = *device_id;
+                sink.send(&device)
+            }
+            None => sink.send(device),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::axis::AxisKind;
+use crate::hat::HatAngle;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// A parsed input script, see [Script::parse].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    pub statements: Vec<Statement>,
+}
+
+/// A command with the line it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub line: usize,
+    pub command: Command,
+}
+
+/// A single script command.
+///
+/// - `Device`: selects the device for the following commands, `dev 1:`.
+/// - `Press` / `Release`: presses or releases buttons by ID, `press b3 b4`.
+/// - `ReleaseAll`: releases all buttons of the device, `release all`.
+/// - `Axis`: sets an axis to a value in the range -1.0..=1.0, optionally moving there linearly, `axis X 0.5 over 200ms`.
+///   A move blocks like `wait` for its duration.
+/// - `Hat`: points a hat in a direction or centers it (`None`), `hat 1 east`.
+/// - `Wait`: keeps the current state for a duration, `wait 1s` or `hold 80ms`.
+/// - `Reset`: resets the device like the driver does, centering X, Y, Z and all hats, setting other axes to 0 and releasing all buttons, `reset`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Device(u32),
+    Press(Vec<u8>),
+    Release(Vec<u8>),
+    ReleaseAll,
+    Axis {
+        kind: AxisKind,
+        value: f32,
+        over: Option<Duration>,
+    },
+    Hat {
+        hat_id: u8,
+        direction: Option<HatAngle>,
+    },
+    Wait(Duration),
+    Reset,
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for statement in &self.statements {
+            f.write_fmt(format_args!("{}\n", statement.command))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Command {
+    /// Canonical script syntax of the command.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let buttons = |buttons: &[u8]| {
+            buttons
+                .iter()
+                .map(|button| format!("b{}", button))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        match self {
+            Command::Device(device_id) => f.write_fmt(format_args!("dev {}:", device_id)),
+            Command::Press(ids) => f.write_fmt(format_args!("press {}", buttons(ids))),
+            Command::Release(ids) => f.write_fmt(format_args!("release {}", buttons(ids))),
+            Command::ReleaseAll => f.write_str("release all"),
+            Command::Axis { kind, value, over } => {
+                f.write_fmt(format_args!("axis {} {}", kind.display_name(), value))?;
+                match over {
+                    Some(duration) => {
+                        f.write_fmt(format_args!(" over {}", DurationText(*duration)))
+                    }
+                    None => Ok(()),
+                }
+            }
+            Command::Hat { hat_id, direction } => match direction {
+                Some(angle) => f.write_fmt(format_args!("hat {} {}deg", hat_id, angle.degrees())),
+                None => f.write_fmt(format_args!("hat {} center", hat_id)),
+            },
+            Command::Wait(duration) => {
+                f.write_fmt(format_args!("wait {}", DurationText(*duration)))
+            }
+            Command::Reset => f.write_str("reset"),
+        }
+    }
+}
+
+/// Duration in milliseconds, or microseconds if it has a fraction of a millisecond.
+struct DurationText(Duration);
+
+impl Display for DurationText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let micros = self.0.as_micros();
+        if micros.is_multiple_of(1000) {
+            f.write_fmt(format_args!("{}ms", micros / 1000))
+        } else {
+            f.write_fmt(format_args!("{}us", micros))
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::axis::Axis;
+use crate::button::ButtonState;
+use crate::device::Device;
+use crate::error::{AppError, Error};
+use crate::hat::{FourWayHat, HatState, HatType};
+use crate::replay::DeviceSink;
+use crate::script::{Command, Script, Statement};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Linear axis move of an `axis ... over ...` command.
+#[derive(Debug, Clone, PartialEq)]
+struct AxisRamp {
+    device_id: u32,
+    axis_index: usize,
+    from: i32,
+    to: i32,
+    start: Duration,
+    duration: Duration,
+}
+
+impl AxisRamp {
+    fn end(&self) -> Duration {
+        self.start + self.duration
+    }
+
+    fn value(&self, now: Duration) -> i32 {
+        if now >= self.end() {
+            return self.to;
+        }
+
+        let t = now.saturating_sub(self.start).as_secs_f64() / self.duration.as_secs_f64();
+        (self.from as f64 + (self.to - self.from) as f64 * t).round() as i32
+    }
+}
+
+/// Runs a [Script] against a set of devices and sends their states to a [DeviceSink].
+///
+/// Add the devices the script may select, e.g. from [VJoy::get_device_state](crate::VJoy::get_device_state).
+/// Like [Replayer](crate::Replayer), the interpreter runs on its own clock: drive it via [advance](Self::advance) in steps,
+/// in real time via [play](Self::play), or without waiting via [run](Self::run).
+/// Changed devices are sent before each wait and at the end of each step.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, Error};
+/// # use vjoy::script::{Interpreter, Script};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// let script = Script::parse("dev 1: press b3; hold 80ms; axis X 0.5 over 200ms; hat 1 east; wait 1s; release all")?;
+/// Interpreter::new(&script)
+///     .device(vjoy.get_device_state(1)?)
+///     .play(&mut vjoy)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interpreter<'a> {
+    statements: &'a [Statement],
+    devices: BTreeMap<u32, Device>,
+    position: usize,
+    device_id: Option<u32>,
+    time: Duration,
+    resume_at: Duration,
+    ramp: Option<AxisRamp>,
+    ramp_step: Duration,
+}
+
+impl<'a> Interpreter<'a> {
+    #[profiling::function]
+    pub fn new(script: &'a Script) -> Self {
+        Self {
+            statements: &script.statements,
+            devices: BTreeMap::new(),
+            position: 0,
+            device_id: None,
+            time: Duration::ZERO,
+            resume_at: Duration::ZERO,
+            ramp: None,
+            ramp_step: Duration::from_millis(10),
+        }
+    }
+
+    /// Adds a device the script can select. A device with the same ID is replaced.
+    #[profiling::function]
+    pub fn device(mut self, device: Device) -> Self {
+        self.devices.insert(device.id, device);
+        self
+    }
+
+    /// Interval at which [play](Self::play) sends intermediate states of axis moves. Defaults to 10 ms.
+    #[profiling::function]
+    pub fn ramp_step(mut self, ramp_step: Duration) -> Self {
+        self.ramp_step = ramp_step.max(Duration::from_micros(1));
+        self
+    }
+
+    /// Current state of a device.
+    #[profiling::function]
+    pub fn state(&self, device_id: u32) -> Option<&Device> {
+        self.devices.get(&device_id)
+    }
+
+    /// Time since the start of the script.
+    #[profiling::function]
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Whether all commands were executed and a trailing wait or axis move has elapsed.
+    #[profiling::function]
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.statements.len() && self.ramp.is_none() && self.time >= self.resume_at
+    }
+
+    /// Advances the script clock by `elapsed`, executes all commands that became due and sends changed devices.
+    ///
+    /// Returns the number of sent states. On an error, e.g. a button the device lacks,
+    /// the error is returned and the script continues after the failed command on the next call.
+    #[profiling::function]
+    pub fn advance<S: DeviceSink + ?Sized>(
+        &mut self,
+        sink: &mut S,
+        elapsed: Duration,
+    ) -> Result<usize, Error> {
+        self.time += elapsed;
+        let mut sent = 0;
+
+        loop {
+            if let Some(ramp) = &self.ramp {
+                let value = ramp.value(self.time);
+                let (device_id, axis_index, end) = (ramp.device_id, ramp.axis_index, ramp.end());
+                if let Some(device) = self.devices.get_mut(&device_id) {
+                    device.axes[axis_index].value = value;
+                    device.dirty = true;
+                }
+                if self.time < end {
+                    break;
+                }
+                self.ramp = None;
+            }
+
+            let Some(statement) = self.statements.get(self.position) else {
+                break;
+            };
+            if self.resume_at > self.time {
+                break;
+            }
+            self.position += 1;
+
+            match &statement.command {
+                Command::Wait(duration) => {
+                    sent += self.flush(sink)?;
+                    self.resume_at += *duration;
+                }
+                Command::Axis {
+                    kind,
+                    value,
+                    over: Some(duration),
+                } => {
+                    sent += self.flush(sink)?;
+                    let device = self.selected_device(statement)?;
+                    let device_id = device.id;
+                    let Some(axis_index) = device
+                        .axes
+                        .iter()
+                        .position(|axis| axis.kind() == Some(*kind))
+                    else {
+                        return Err(Error::App(AppError::AxisNotFound(device_id, kind.id())));
+                    };
+
+                    self.ramp = Some(AxisRamp {
+                        device_id,
+                        axis_index,
+                        from: device.axes[axis_index].value,
+                        to: Axis::from_normalized(*value),
+                        start: self.resume_at,
+                        duration: *duration,
+                    });
+                    self.resume_at += *duration;
+                }
+                command => self.execute(statement, command)?,
+            }
+        }
+
+        sent += self.flush(sink)?;
+        Ok(sent)
+    }
+
+    /// Runs the rest of the script in real time, blocking until it is finished.
+    #[profiling::function]
+    pub fn play<S: DeviceSink + ?Sized>(&mut self, sink: &mut S) -> Result<(), Error> {
+        let start = Instant::now();
+        let mut played = Duration::ZERO;
+
+        while !self.is_finished() {
+            let next = match &self.ramp {
+                Some(ramp) => (self.time + self.ramp_step).min(ramp.end()),
+                None => self.resume_at,
+            };
+            // Deadlines are measured from the start to avoid drift over long scripts
+            let due = played + next.saturating_sub(self.time);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+
+            let now = start.elapsed();
+            self.advance(sink, now - played)?;
+            played = now;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the rest of the script without waiting, e.g. to check the final states in tests.
+    ///
+    /// States are sent at the end of each wait and axis move, without intermediate states of moves.
+    #[profiling::function]
+    pub fn run<S: DeviceSink + ?Sized>(&mut self, sink: &mut S) -> Result<(), Error> {
+        while !self.is_finished() {
+            let next = match &self.ramp {
+                Some(ramp) => ramp.end(),
+                None => self.resume_at,
+            };
+            self.advance(sink, next.saturating_sub(self.time))?;
+        }
+
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn execute(&mut self, statement: &Statement, command: &Command) -> Result<(), Error> {
+        if let Command::Device(device_id) = command {
+            if !self.devices.contains_key(device_id) {
+                return Err(Error::App(AppError::DeviceNotFound(*device_id)));
+            }
+            self.device_id = Some(*device_id);
+            return Ok(());
+        }
+
+        let device = self.selected_device(statement)?;
+        match command {
+            Command::Press(buttons) => {
+                for button in buttons {
+                    device.set_button(*button, ButtonState::Pressed)?;
+                }
+            }
+            Command::Release(buttons) => {
+                for button in buttons {
+                    device.set_button(*button, ButtonState::Released)?;
+                }
+            }
+            Command::ReleaseAll => {
+                for button in device.buttons_mut() {
+                    button.set(ButtonState::Released);
+                }
+            }
+            Command::Axis { kind, value, .. } => {
+                let device_id = device.id;
+                let Some(axis) = device.axes_mut().find(|axis| axis.kind() == Some(*kind)) else {
+                    return Err(Error::App(AppError::AxisNotFound(device_id, kind.id())));
+                };
+                axis.set_normalized(*value);
+            }
+            Command::Hat { hat_id, direction } => {
+                let hat_type = match device.hats.get((*hat_id as usize).wrapping_sub(1)) {
+                    Some(hat) => hat.hat_type,
+                    None => return Err(Error::App(AppError::HatNotFound(device.id, *hat_id))),
+                };
+                let state = match (hat_type, direction) {
+                    (_, None) => hat_type.centered(),
+                    (HatType::Discrete, Some(angle)) => {
+                        HatState::Discrete(FourWayHat::nearest(*angle))
+                    }
+                    (HatType::Continuous, Some(angle)) => HatState::from(*angle),
+                };
+                device.set_hat(*hat_id, state)?;
+            }
+            Command::Reset => device.reset_to_driver_defaults(),
+            Command::Device(_) | Command::Wait(_) => {}
+        }
+
+        Ok(())
+    }
+
+    fn selected_device(&mut self, statement: &Statement) -> Result<&mut Device, Error> {
+        let Some(device_id) = self.device_id else {
+            return Err(Error::App(AppError::InvalidScript(
+                statement.line,
+                "no device selected".to_string(),
+            )));
+        };
+
+        self.devices
+            .get_mut(&device_id)
+            .ok_or(Error::App(AppError::DeviceNotFound(device_id)))
+    }
+
+    /// Sends all changed devices.
+    fn flush<S: DeviceSink + ?Sized>(&mut self, sink: &mut S) -> Result<usize, Error> {
+        let mut sent = 0;
+        for device in self.devices.values_mut().filter(|device| device.dirty) {
+            sink.send(device)?;
+            device.dirty = false;
+            sent += 1;
+        }
+
+        Ok(sent)
+    }
+}
@@ -0,0 +1,21 @@
+//! Input scripts to drive devices from text, e.g. for test fixtures, macros and demos.
+//!
+//! A [Script] is parsed from a small line-based language, see [Script::parse] and [Command]:
+//!
+//! ```text
+//! dev 1: press b3; hold 80ms
+//! axis X 0.5 over 200ms   # moves linearly and blocks like a wait
+//! hat 1 east; wait 1s
+//! release all
+//! ```
+//!
+//! The [Interpreter] executes a script on its own clock and sends device states to any [DeviceSink](crate::DeviceSink),
+//! so scripts run against the driver or, in tests, against a [MemorySink](crate::MemorySink).
+
+mod ast;
+pub use ast::*;
+
+mod parser;
+
+mod interpreter;
+pub use interpreter::*;
@@ -0,0 +1,195 @@
+use crate::axis::AxisKind;
+use crate::error::{AppError, Error};
+use crate::hat::HatAngle;
+use crate::script::{Command, Script, Statement};
+use std::str::FromStr;
+use std::time::Duration;
+
+impl Script {
+    /// Parses a script.
+    ///
+    /// Commands are separated by `;` or line breaks, `#` starts a comment until the end of the line.
+    /// Keywords, axis names and hat directions are case-insensitive. See [Command] for the syntax of each command.
+    /// Durations are given in `us`, `ms` or `s`, e.g. `80ms` or `1.5s`.
+    /// Hat directions are `north`, `northeast`, ..., their abbreviations `n`, `ne`, ..., an angle like `45deg`, or `center`.
+    ///
+    /// Every script needs to select a device via `dev <id>:` before its first device command.
+    #[profiling::function]
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut statements = Vec::new();
+        let mut device_selected = false;
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let code = line.split('#').next().unwrap_or_default();
+
+            for segment in code.split(';') {
+                let mut tokens: Vec<String> = segment
+                    .split_whitespace()
+                    .map(|token| token.to_lowercase())
+                    .collect();
+                if tokens.is_empty() {
+                    continue;
+                }
+
+                if tokens[0] == "dev" {
+                    let device_id = tokens
+                        .get(1)
+                        .map(|token| token.trim_end_matches(':'))
+                        .and_then(|token| token.parse::<u32>().ok())
+                        .ok_or_else(|| invalid(line_number, "expected device ID after dev"))?;
+                    let mut rest = 2;
+                    if tokens.get(2).is_some_and(|token| token == ":") {
+                        rest = 3;
+                    }
+
+                    statements.push(Statement {
+                        line: line_number,
+                        command: Command::Device(device_id),
+                    });
+                    device_selected = true;
+                    tokens.drain(..rest);
+                    if tokens.is_empty() {
+                        continue;
+                    }
+                }
+
+                if !device_selected {
+                    return Err(invalid(
+                        line_number,
+                        "no device selected, start with dev <id>:",
+                    ));
+                }
+                statements.push(Statement {
+                    line: line_number,
+                    command: parse_command(line_number, &tokens)?,
+                });
+            }
+        }
+
+        Ok(Self { statements })
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+fn parse_command(line: usize, tokens: &[String]) -> Result<Command, Error> {
+    let arguments: Vec<&str> = tokens[1..].iter().map(String::as_str).collect();
+
+    match (tokens[0].as_str(), arguments.as_slice()) {
+        ("press", buttons) if !buttons.is_empty() => {
+            Ok(Command::Press(parse_buttons(line, buttons)?))
+        }
+        ("release", ["all"]) => Ok(Command::ReleaseAll),
+        ("release", buttons) if !buttons.is_empty() => {
+            Ok(Command::Release(parse_buttons(line, buttons)?))
+        }
+        ("axis", [kind, value, rest @ ..]) => {
+            let kind = parse_axis(kind).ok_or_else(|| invalid(line, "unknown axis"))?;
+            let value = value
+                .parse::<f32>()
+                .ok()
+                .filter(|value| (-1.0..=1.0).contains(value))
+                .ok_or_else(|| invalid(line, "axis value must be in the range -1.0..=1.0"))?;
+            let over = match rest {
+                [] => None,
+                ["over", duration] => Some(parse_duration(line, duration)?),
+                _ => {
+                    return Err(invalid(
+                        line,
+                        "expected over <duration> after the axis value",
+                    ));
+                }
+            };
+
+            Ok(Command::Axis { kind, value, over })
+        }
+        ("hat", [hat_id, direction]) => {
+            let hat_id = hat_id
+                .parse::<u8>()
+                .map_err(|_| invalid(line, "invalid hat ID"))?;
+            let direction =
+                parse_direction(direction).ok_or_else(|| invalid(line, "invalid hat direction"))?;
+
+            Ok(Command::Hat { hat_id, direction })
+        }
+        ("wait" | "hold", [duration]) => Ok(Command::Wait(parse_duration(line, duration)?)),
+        ("reset", []) => Ok(Command::Reset),
+        ("press" | "release" | "axis" | "hat" | "wait" | "hold" | "reset", _) => Err(invalid(
+            line,
+            &format!("invalid arguments for {}", tokens[0]),
+        )),
+        (command, _) => Err(invalid(line, &format!("unknown command {}", command))),
+    }
+}
+
+fn parse_buttons(line: usize, buttons: &[&str]) -> Result<Vec<u8>, Error> {
+    buttons
+        .iter()
+        .map(|button| {
+            button
+                .strip_prefix('b')
+                .and_then(|id| id.parse::<u8>().ok())
+                .ok_or_else(|| invalid(line, &format!("invalid button {}, expected b<id>", button)))
+        })
+        .collect()
+}
+
+fn parse_axis(name: &str) -> Option<AxisKind> {
+    match name {
+        "dial" | "slider2" => Some(AxisKind::Dial),
+        _ => AxisKind::ALL
+            .into_iter()
+            .find(|kind| kind.display_name().eq_ignore_ascii_case(name)),
+    }
+}
+
+fn parse_direction(direction: &str) -> Option<Option<HatAngle>> {
+    let hundredths = match direction {
+        "center" | "centered" | "c" => return Some(None),
+        "north" | "n" => 0,
+        "northeast" | "ne" => 4500,
+        "east" | "e" => 9000,
+        "southeast" | "se" => 13500,
+        "south" | "s" => 18000,
+        "southwest" | "sw" => 22500,
+        "west" | "w" => 27000,
+        "northwest" | "nw" => 31500,
+        _ => {
+            let degrees = direction.strip_suffix("deg")?.parse::<f32>().ok()?;
+            return HatAngle::from_degrees(degrees).ok().map(Some);
+        }
+    };
+
+    HatAngle::from_hundredths(hundredths).ok().map(Some)
+}
+
+fn parse_duration(line: usize, duration: &str) -> Result<Duration, Error> {
+    let (value, nanos_per_unit) = if let Some(value) = duration.strip_suffix("us") {
+        (value, 1e3)
+    } else if let Some(value) = duration.strip_suffix("ms") {
+        (value, 1e6)
+    } else if let Some(value) = duration.strip_suffix('s') {
+        (value, 1e9)
+    } else {
+        return Err(invalid(line, "duration needs a unit of us, ms or s"));
+    };
+
+    value
+        .parse::<f64>()
+        .ok()
+        .map(|value| (value * nanos_per_unit).round())
+        .filter(|nanos| (0.0..=u64::MAX as f64).contains(nanos))
+        .map(|nanos| Duration::from_nanos(nanos as u64))
+        .ok_or_else(|| invalid(line, &format!("invalid duration {}", duration)))
+}
+
+fn invalid(line: usize, reason: &str) -> Error {
+    Error::App(AppError::InvalidScript(line, reason.to_string()))
+}
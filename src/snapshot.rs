@@ -0,0 +1,36 @@
+use crate::device::Device;
+use std::fmt::Display;
+
+/// States of all devices of a [VJoy](crate::VJoy) instance at one point in time.
+///
+/// Take a snapshot via [VJoy::snapshot](crate::VJoy::snapshot) and re-apply it via [VJoy::restore](crate::VJoy::restore),
+/// e.g. to restore trims, toggles and axis holds after a game restart.
+/// With the `serde` feature, snapshots can be serialized to any serde format.
+///
+/// ```no_run
+/// # use vjoy::{VJoy, Error};
+/// # let mut vjoy = VJoy::from_default_dll_location()?;
+/// let snapshot = vjoy.snapshot();
+/// // ...
+/// vjoy.restore(&snapshot)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VJoySnapshot {
+    pub devices: Vec<Device>,
+}
+
+impl VJoySnapshot {
+    #[profiling::function]
+    pub fn device(&self, device_id: u32) -> Option<&Device> {
+        self.devices.iter().find(|device| device.id == device_id)
+    }
+}
+
+impl Display for VJoySnapshot {
+    #[profiling::function]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Snapshot of {} devices", self.devices.len()))
+    }
+}
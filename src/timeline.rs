@@ -0,0 +1,420 @@
+use crate::axis::AxisKind;
+use crate::button::ButtonState;
+use crate::device::DeviceBuilder;
+use crate::error::{AppError, Error};
+use crate::hat::{FourWayHat, HatState, HatType};
+use crate::recording::{RecordedState, Recording};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Column of a timeline row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Column {
+    Time,
+    Device,
+    Button(u8),
+    Axis(AxisKind),
+    Hat(u8),
+}
+
+impl Column {
+    fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        match name {
+            "time_us" => return Some(Column::Time),
+            "device" => return Some(Column::Device),
+            _ => {}
+        }
+
+        if let Some(button_id) = name.strip_prefix('b').and_then(|id| id.parse::<u8>().ok()) {
+            return (button_id > 0).then_some(Column::Button(button_id));
+        }
+        if let Some(hat_id) = name
+            .strip_prefix("hat")
+            .and_then(|id| id.parse::<u8>().ok())
+        {
+            return (hat_id > 0).then_some(Column::Hat(hat_id));
+        }
+
+        AxisKind::ALL
+            .into_iter()
+            .find(|kind| {
+                kind.display_name().eq_ignore_ascii_case(name)
+                    || (*kind == AxisKind::Dial && name.eq_ignore_ascii_case("dial"))
+            })
+            .map(Column::Axis)
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Column::Time => "time_us".to_string(),
+            Column::Device => "device".to_string(),
+            Column::Button(button_id) => format!("b{}", button_id),
+            Column::Axis(kind) => kind.display_name().to_string(),
+            Column::Hat(hat_id) => format!("hat{}", hat_id),
+        }
+    }
+
+    /// Columns for all components of all devices in the recording.
+    fn for_recording(recording: &Recording) -> Vec<Column> {
+        let mut axes: Vec<AxisKind> = Vec::new();
+        let mut num_buttons = 0;
+        let mut num_hats = 0;
+        for state in &recording.states {
+            let device = &state.device;
+            num_buttons = num_buttons.max(device.buttons.len());
+            num_hats = num_hats.max(device.hats.len());
+            for kind in device.axes.iter().filter_map(|axis| axis.kind()) {
+                if !axes.contains(&kind) {
+                    axes.push(kind);
+                }
+            }
+        }
+        axes.sort_unstable();
+
+        let mut columns = vec![Column::Time, Column::Device];
+        columns.extend((1..=num_buttons).map(|button_id| Column::Button(button_id as u8)));
+        columns.extend(axes.into_iter().map(Column::Axis));
+        columns.extend((1..=num_hats).map(|hat_id| Column::Hat(hat_id as u8)));
+        columns
+    }
+
+    /// Cell of the column for a state, empty if the device lacks the component.
+    fn cell(&self, state: &RecordedState) -> String {
+        let device = &state.device;
+        match self {
+            Column::Time => state.timestamp.as_micros().to_string(),
+            Column::Device => device.id.to_string(),
+            Column::Button(button_id) => match device.buttons.get(*button_id as usize - 1) {
+                Some(button) if button.state == ButtonState::Pressed => "1".to_string(),
+                Some(_) => "0".to_string(),
+                None => String::new(),
+            },
+            Column::Axis(kind) => device
+                .axes
+                .iter()
+                .find(|axis| axis.kind() == Some(*kind))
+                .map_or(String::new(), |axis| axis.value.to_string()),
+            Column::Hat(hat_id) => match device.hats.get(*hat_id as usize - 1) {
+                Some(hat) => match hat.state {
+                    HatState::Discrete(direction) => match direction {
+                        FourWayHat::Centered => "C",
+                        FourWayHat::North => "N",
+                        FourWayHat::East => "E",
+                        FourWayHat::South => "S",
+                        FourWayHat::West => "W",
+                    }
+                    .to_string(),
+                    HatState::Continuous(value) if value >= 36000 => "-1".to_string(),
+                    HatState::Continuous(value) => value.to_string(),
+                },
+                None => String::new(),
+            },
+        }
+    }
+}
+
+/// Builds the state of one row from its cells. Empty cells mark components the device lacks.
+fn state_from_cells(row: usize, cells: &[(Column, &str)]) -> Result<RecordedState, Error> {
+    let invalid = |reason: &str| Error::App(AppError::InvalidTimelineRow(row, reason.to_string()));
+
+    let mut timestamp = None;
+    let mut device_id = None;
+    let mut buttons: Vec<(u8, ButtonState)> = Vec::new();
+    let mut axes: Vec<(AxisKind, i32)> = Vec::new();
+    let mut hats: Vec<(u8, HatState)> = Vec::new();
+
+    for (column, cell) in cells {
+        let cell = cell.trim();
+        if cell.is_empty() {
+            continue;
+        }
+
+        match column {
+            Column::Time => {
+                let micros = cell
+                    .parse::<u64>()
+                    .map_err(|_| invalid("invalid time_us"))?;
+                timestamp = Some(Duration::from_micros(micros));
+            }
+            Column::Device => {
+                device_id = Some(cell.parse::<u32>().map_err(|_| invalid("invalid device"))?);
+            }
+            Column::Button(button_id) => {
+                let state = match cell {
+                    "0" => ButtonState::Released,
+                    "1" => ButtonState::Pressed,
+                    _ => return Err(invalid(&format!("invalid state of b{}", button_id))),
+                };
+                buttons.push((*button_id, state));
+            }
+            Column::Axis(kind) => {
+                let value = cell
+                    .parse::<i32>()
+                    .map_err(|_| invalid(&format!("invalid value of axis {}", kind)))?;
+                axes.push((*kind, value));
+            }
+            Column::Hat(hat_id) => {
+                let state = match cell.to_ascii_uppercase().as_str() {
+                    "C" => HatState::Discrete(FourWayHat::Centered),
+                    "N" => HatState::Discrete(FourWayHat::North),
+                    "E" => HatState::Discrete(FourWayHat::East),
+                    "S" => HatState::Discrete(FourWayHat::South),
+                    "W" => HatState::Discrete(FourWayHat::West),
+                    "-1" => HatState::Continuous(u32::MAX),
+                    value => value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|value| *value < 36000)
+                        .map(HatState::Continuous)
+                        .ok_or_else(|| invalid(&format!("invalid state of hat{}", hat_id)))?,
+                };
+                hats.push((*hat_id, state));
+            }
+        }
+    }
+
+    let timestamp = timestamp.ok_or_else(|| invalid("missing time_us"))?;
+    let device_id = device_id.ok_or_else(|| invalid("missing device"))?;
+
+    hats.sort_unstable_by_key(|(hat_id, _)| *hat_id);
+    if hats
+        .iter()
+        .enumerate()
+        .any(|(index, (hat_id, _))| *hat_id as usize != index + 1)
+    {
+        return Err(invalid("hats need to be numbered without gaps from hat1"));
+    }
+
+    let num_buttons = buttons
+        .iter()
+        .map(|(button_id, _)| *button_id)
+        .max()
+        .unwrap_or(0);
+    let kinds: Vec<AxisKind> = axes.iter().map(|(kind, _)| *kind).collect();
+    let hat_types: Vec<HatType> = hats.iter().map(|(_, state)| state.hat_type()).collect();
+
+    let mut builder = DeviceBuilder::new(device_id)
+        .buttons(num_buttons as u32)
+        .axes(&kinds);
+    for hat_type in hat_types {
+        builder = builder.hat(hat_type);
+    }
+    let mut device = builder.build().map_err(|e| invalid(&e.to_string()))?;
+    if device.axes.len() != axes.len() {
+        return Err(invalid("duplicate axis"));
+    }
+
+    for (button_id, state) in buttons {
+        device.buttons[button_id as usize - 1].state = state;
+    }
+    for (kind, value) in axes {
+        if let Some(axis) = device
+            .axes
+            .iter_mut()
+            .find(|axis| axis.kind() == Some(kind))
+        {
+            axis.value = value;
+        }
+    }
+    for (hat, (_, state)) in device.hats.iter_mut().zip(hats) {
+        hat.state = state;
+    }
+
+    Ok(RecordedState { timestamp, device })
+}
+
+fn header(row: usize, names: &[&str]) -> Result<Vec<Column>, Error> {
+    let mut columns: Vec<Column> = Vec::new();
+    for name in names {
+        let column = Column::parse(name).ok_or_else(|| {
+            Error::App(AppError::InvalidTimelineRow(
+                row,
+                format!("unknown column {}", name.trim()),
+            ))
+        })?;
+        if columns.contains(&column) {
+            return Err(Error::App(AppError::InvalidTimelineRow(
+                row,
+                format!("duplicate column {}", name.trim()),
+            )));
+        }
+        columns.push(column);
+    }
+
+    Ok(columns)
+}
+
+impl Recording {
+    /// Reads a recording from a timeline in CSV.
+    ///
+    /// Timelines are a tabular format for spreadsheets, notebooks and hand-authored test inputs. A timeline has one row per recorded state with these columns:
+    ///
+    /// - `time_us`: timestamp in microseconds.
+    /// - `device`: device ID.
+    /// - `b1`, `b2`, ...: button state as `0` (released) or `1` (pressed).
+    /// - `X`, `Y`, `Z`, `Rx`, `Ry`, `Rz`, `Slider`, `Dial/Slider2`: raw axis value. Names are case-insensitive on import, `Dial` is accepted as well.
+    /// - `hat1`, `hat2`, ...: discrete hats as `N`, `E`, `S`, `W` or `C` (centered),
+    ///   continuous hats as the angle in 1/100° or `-1` (centered).
+    ///
+    /// Exported timelines contain a column for every component of any device in the recording,
+    /// with empty cells for components a device lacks. On import, the layout of each device is derived from its non-empty cells,
+    /// so columns can be omitted, reordered or left empty. Missing buttons below the highest given button are released.
+    ///
+    /// In CSV, the first line names the columns and every following line is one row. Empty lines and lines starting with `#` are ignored.
+    /// With the `json` feature, timelines are also available as JSON lines, one object per row with the column names as keys.
+    /// Buttons, axes, continuous hats and the time are numbers, discrete hats are strings, and components a device lacks are left out.
+    ///
+    /// ```text
+    /// time_us,device,b1,b2,X,Y,hat1
+    /// 0,1,0,0,16384,16384,C
+    /// 80000,1,1,0,24576,16384,E
+    /// ```
+    #[profiling::function]
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut recording = Self::new();
+        let mut columns: Option<Vec<Column>> = None;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let cells: Vec<&str> = line.split(',').collect();
+            let Some(columns) = &columns else {
+                columns = Some(header(index + 1, &cells)?);
+                continue;
+            };
+            if cells.len() != columns.len() {
+                return Err(Error::App(AppError::InvalidTimelineRow(
+                    index + 1,
+                    format!("expected {} cells, found {}", columns.len(), cells.len()),
+                )));
+            }
+
+            let cells: Vec<(Column, &str)> = columns.iter().copied().zip(cells).collect();
+            recording.states.push(state_from_cells(index + 1, &cells)?);
+        }
+
+        Ok(recording)
+    }
+
+    /// Writes the recording as a timeline in CSV, see [read_csv](Self::read_csv) for the format.
+    #[profiling::function]
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let columns = Column::for_recording(self);
+        let names: Vec<String> = columns.iter().map(Column::name).collect();
+        writeln!(writer, "{}", names.join(",")).map_err(Error::Io)?;
+
+        for state in &self.states {
+            let cells: Vec<String> = columns.iter().map(|column| column.cell(state)).collect();
+            writeln!(writer, "{}", cells.join(",")).map_err(Error::Io)?;
+        }
+
+        writer.flush().map_err(Error::Io)
+    }
+
+    #[profiling::function]
+    pub fn load_csv<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+        Self::read_csv(BufReader::new(file))
+    }
+
+    #[profiling::function]
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        self.write_csv(BufWriter::new(file))
+    }
+
+    /// Reads a recording from a timeline in JSON lines, see [read_csv](Self::read_csv) for the format.
+    #[cfg(feature = "json")]
+    #[profiling::function]
+    pub fn read_json_lines<R: BufRead>(reader: R) -> Result<Self, Error> {
+        use serde_json::Value;
+
+        let mut recording = Self::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let invalid =
+                |reason: String| Error::App(AppError::InvalidTimelineRow(index + 1, reason));
+            let row: serde_json::Map<String, Value> =
+                serde_json::from_str(line).map_err(|e| invalid(e.to_string()))?;
+
+            let names: Vec<&str> = row.keys().map(String::as_str).collect();
+            let columns = header(index + 1, &names)?;
+            let values = row
+                .values()
+                .map(|value| match value {
+                    Value::Null => Ok(String::new()),
+                    Value::Number(number) => Ok(number.to_string()),
+                    Value::String(text) => Ok(text.clone()),
+                    _ => Err(invalid(format!("invalid value {}", value))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let cells: Vec<(Column, &str)> = columns
+                .into_iter()
+                .zip(values.iter().map(String::as_str))
+                .collect();
+            recording.states.push(state_from_cells(index + 1, &cells)?);
+        }
+
+        Ok(recording)
+    }
+
+    /// Writes the recording as a timeline in JSON lines, with keys in column order.
+    #[cfg(feature = "json")]
+    #[profiling::function]
+    pub fn write_json_lines<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let columns = Column::for_recording(self);
+
+        for state in &self.states {
+            let fields: Vec<String> = columns
+                .iter()
+                .filter_map(|column| {
+                    let cell = column.cell(state);
+                    if cell.is_empty() {
+                        return None;
+                    }
+
+                    // Discrete hats are letters, everything else is numeric
+                    let value = match cell.parse::<i64>() {
+                        Ok(number) => serde_json::Value::from(number),
+                        Err(_) => serde_json::Value::from(cell),
+                    };
+                    Some(format!(
+                        "{}:{}",
+                        serde_json::Value::from(column.name()),
+                        value
+                    ))
+                })
+                .collect();
+            writeln!(writer, "{{{}}}", fields.join(",")).map_err(Error::Io)?;
+        }
+
+        writer.flush().map_err(Error::Io)
+    }
+
+    #[cfg(feature = "json")]
+    #[profiling::function]
+    pub fn load_json_lines<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+        Self::read_json_lines(BufReader::new(file))
+    }
+
+    #[cfg(feature = "json")]
+    #[profiling::function]
+    pub fn save_json_lines<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        self.write_json_lines(BufWriter::new(file))
+    }
+}
@@ -1,11 +1,46 @@
-use crate::axis::Axis;
-use crate::button::{Button, ButtonState};
-use crate::device::Device;
+use crate::alias::DeviceAliases;
+use crate::axis::AxisKind;
+use crate::button::ButtonState;
+use crate::capabilities::DeviceCapabilities;
+use crate::device::{Device, DeviceBuilder, DeviceDiff};
 use crate::error::{AppError, Error, FFIError};
-use crate::hat::HatState;
-use crate::{FourWayHat, Hat};
-use log::trace;
-use vjoy_sys::{AXES_DISPLAY_NAMES, AXES_HID_USAGE, JOYSTICK_POSITION, VjdStat};
+use crate::ffb::dispatch::{FfbDispatcher, ffb_trampoline};
+use crate::ffb::{FfbPacket, FfbRecorder, FfbReport, RawFfbPacket};
+use crate::hat::{HatState, HatType};
+use crate::query::{DeviceMatch, DeviceQuery, DeviceStatus};
+use crate::recording::Recorder;
+use crate::replay::DeviceSink;
+use crate::snapshot::VJoySnapshot;
+use log::{error, trace};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::mpsc::Receiver;
+use vjoy_sys::{AXES_HID_USAGE, JOYSTICK_POSITION, PVOID, VjdStat};
+
+/// Location of vJoyInterface.dll in a default vJoy installation.
+pub const DEFAULT_DLL_LOCATION: &str = "C:/Program Files/vJoy/x64/vJoyInterface.dll";
+
+/// Options for [VJoy::with_options].
+///
+/// - `dll_location`: Path of vJoyInterface.dll.
+/// - `reset_on_acquire`: Resets each device to neutral when it is acquired, e.g. to release buttons a crashed feeder left pressed.
+/// - `reset_on_drop`: Resets each device to neutral before it is relinquished when [VJoy] is dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VJoyOptions {
+    pub dll_location: String,
+    pub reset_on_acquire: bool,
+    pub reset_on_drop: bool,
+}
+
+impl Default for VJoyOptions {
+    fn default() -> Self {
+        Self {
+            dll_location: DEFAULT_DLL_LOCATION.to_string(),
+            reset_on_acquire: false,
+            reset_on_drop: false,
+        }
+    }
+}
 
 /// Main entry for this crate and controller for all vJoy devices.
 ///
@@ -40,18 +75,43 @@ use vjoy_sys::{AXES_DISPLAY_NAMES, AXES_HID_USAGE, JOYSTICK_POSITION, VjdStat};
 pub struct VJoy {
     ffi: vjoy_sys::vJoyInterface,
     devices: Vec<Device>,
+    /// Last state sent to the driver per device, `None` until the first update.
+    committed: Vec<Option<Device>>,
+    capabilities: Vec<DeviceCapabilities>,
+    ffb: Option<&'static Mutex<FfbDispatcher>>,
+    aliases: DeviceAliases,
+    reset_on_drop: bool,
+    recorder: Option<Recorder>,
 }
 
 impl VJoy {
     #[profiling::function]
     pub fn from_default_dll_location() -> Result<Self, Error> {
-        Self::from_dll_location("C:/Program Files/vJoy/x64/vJoyInterface.dll")
+        Self::with_options(VJoyOptions::default())
     }
 
     #[profiling::function]
     pub fn from_dll_location(path: &str) -> Result<Self, Error> {
-        let mut vjoy = Self::new(path)?;
-        vjoy.fetch_devices();
+        Self::with_options(VJoyOptions {
+            dll_location: path.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// ```no_run
+    /// # use vjoy::{VJoy, VJoyOptions, Error};
+    /// let vjoy = VJoy::with_options(VJoyOptions {
+    ///     reset_on_acquire: true,
+    ///     reset_on_drop: true,
+    ///     ..Default::default()
+    /// })?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn with_options(options: VJoyOptions) -> Result<Self, Error> {
+        let mut vjoy = Self::new(&options.dll_location)?;
+        vjoy.reset_on_drop = options.reset_on_drop;
+        vjoy.fetch_devices(options.reset_on_acquire);
 
         Ok(vjoy)
     }
@@ -73,134 +133,46 @@ impl VJoy {
 
     #[profiling::function]
     pub fn get_device_state(&self, device_id: u32) -> Result<Device, Error> {
-        if device_id == 0 {
-            return Err(Error::App(AppError::DeviceNotFound(device_id)));
-        }
-
-        match self.devices.get((device_id - 1) as usize) {
-            Some(device) => Ok(device.clone()),
-            None => Err(Error::App(AppError::DeviceNotFound(device_id))),
-        }
+        let index = self.device_index(device_id)?;
+        Ok(self.devices[index].clone())
     }
 
     #[profiling::function]
     pub fn get_device_state_ref(&self, device_id: u32) -> Result<&Device, Error> {
-        if device_id == 0 {
-            return Err(Error::App(AppError::DeviceNotFound(device_id)));
-        }
-
-        match self.devices.get((device_id - 1) as usize) {
-            Some(device) => Ok(device),
-            None => Err(Error::App(AppError::DeviceNotFound(device_id))),
-        }
+        let index = self.device_index(device_id)?;
+        Ok(&self.devices[index])
     }
 
     #[profiling::function]
     pub fn get_device_state_mut(&mut self, device_id: u32) -> Result<&mut Device, Error> {
-        if device_id == 0 {
-            return Err(Error::App(AppError::DeviceNotFound(device_id)));
-        }
+        let index = self.device_index(device_id)?;
+        Ok(&mut self.devices[index])
+    }
 
-        match self.devices.get_mut((device_id - 1) as usize) {
-            Some(device) => Ok(device),
-            None => Err(Error::App(AppError::DeviceNotFound(device_id))),
-        }
+    /// Static layout of a device, cached when the device was acquired.
+    #[profiling::function]
+    pub fn capabilities(&self, device_id: u32) -> Result<&DeviceCapabilities, Error> {
+        let index = self.device_index(device_id)?;
+        Ok(&self.capabilities[index])
     }
 
+    /// Sends all [dirty](Device::is_dirty) devices to the driver.
+    ///
+    /// Dirty devices whose state equals the last sent state are marked clean without being sent.
     #[profiling::function]
     pub fn update_all_devices(&mut self) -> Result<(), Error> {
-        for device in self.devices.iter() {
-            // Axes value or default mid-point
-            let axis_data: Vec<i32> = (0..16)
-                .map(|index| {
-                    if let Some(axis) = device.axes.get(index) {
-                        axis.get()
-                    } else {
-                        16384
-                    }
-                })
-                .collect();
-
-            let button_data: Vec<ButtonState> = (0..128)
-                .map(|index| {
-                    if let Some(button) = device.buttons.get(index) {
-                        button.get()
-                    } else {
-                        ButtonState::Released
-                    }
-                })
-                .collect();
-
-            // 4 fields á 32 buttons as single bits
-            let mut button_field_data = [0; 4];
-            for (i, field) in button_field_data.iter_mut().enumerate() {
-                let start = i * 32;
-                let end = i * 32 + 32;
-
-                let buttons = &button_data[start..end];
-                for (bit, state) in buttons.iter().enumerate().take(32) {
-                    if *state == ButtonState::Pressed {
-                        *field |= 0x1 << bit;
-                    }
-                }
+        for (index, device) in self.devices.iter_mut().enumerate() {
+            if !device.dirty {
+                continue;
             }
 
-            let hats_data: Vec<u32> = (0..4)
-                .map(|index| {
-                    if let Some(hat) = device.hats.get(index) {
-                        match hat.get() {
-                            HatState::Continuous(c) => c,
-                            HatState::Discrete(d) => d as u32,
-                        }
-                    } else {
-                        0
-                    }
-                })
-                .collect();
-
-            let mut data = JOYSTICK_POSITION {
-                bDevice: device.id as u8,
-
-                wAxisX: axis_data[0],
-                wAxisY: axis_data[1],
-                wAxisZ: axis_data[2],
-
-                wAxisXRot: axis_data[3],
-                wAxisYRot: axis_data[4],
-                wAxisZRot: axis_data[5],
-
-                wDial: axis_data[6],
-                wSlider: axis_data[7],
-
-                wWheel: axis_data[8],
-                wAccelerator: axis_data[9],
-                wBrake: axis_data[10],
-                wClutch: axis_data[11],
-
-                wSteering: axis_data[12],
-                wAileron: axis_data[13],
-                wRudder: axis_data[14],
-                wThrottle: axis_data[15],
-
-                wAxisVX: 0,
-                wAxisVY: 0,
-                wAxisVZ: 0,
-                wAxisVBRX: 0,
-                wAxisVBRY: 0,
-                wAxisVBRZ: 0,
-
-                lButtons: button_field_data[0],
-                lButtonsEx1: button_field_data[1],
-                lButtonsEx2: button_field_data[2],
-                lButtonsEx3: button_field_data[3],
-
-                bHats: hats_data[0],
-                bHatsEx1: hats_data[1],
-                bHatsEx2: hats_data[2],
-                bHatsEx3: hats_data[3],
-            };
-
-            Self::update_device_data(&self.ffi, device.id, &mut data)?;
+            if self.committed[index].as_ref() != Some(&*device) {
+                let mut data = Self::joystick_position(device);
+                Self::update_device_data(&self.ffi, device.id, &mut data)?;
+                Self::record_state(&mut self.recorder, device);
+                self.committed[index] = Some(device.clone());
+            }
+            device.dirty = false;
         }
 
         Ok(())
@@ -208,115 +180,281 @@ impl VJoy {
 
     #[profiling::function]
     pub fn update_device_state(&mut self, new_device_state: &Device) -> Result<(), Error> {
-        if new_device_state.id == 0 {
-            return Err(Error::App(AppError::DeviceNotFound(new_device_state.id)));
-        }
+        let index = self.device_index(new_device_state.id)?;
+        self.capabilities[index].validate(new_device_state)?;
 
-        let device = match self.devices.get_mut((new_device_state.id - 1) as usize) {
-            Some(device) => device,
-            None => return Err(Error::App(AppError::DeviceNotFound(new_device_state.id))),
-        };
+        // The cache is only written once the driver accepted the state
+        let mut data = Self::joystick_position(new_device_state);
+        Self::update_device_data(&self.ffi, new_device_state.id, &mut data)?;
 
-        *device = new_device_state.clone();
+        Self::record_state(&mut self.recorder, new_device_state);
+        let mut device = new_device_state.clone();
+        device.dirty = false;
+        self.committed[index] = Some(device.clone());
+        self.devices[index] = device;
 
-        // Axes value or default mid-point
-        let axis_data: Vec<i32> = (0..16)
-            .map(|index| {
-                if let Some(axis) = device.axes.get(index) {
-                    axis.get()
-                } else {
-                    16384
-                }
-            })
-            .collect();
+        Ok(())
+    }
 
-        let button_data: Vec<ButtonState> = (0..128)
-            .map(|index| {
-                if let Some(button) = device.buttons.get(index) {
-                    button.get()
-                } else {
-                    ButtonState::Released
-                }
-            })
-            .collect();
+    /// Modifies a device atomically.
+    ///
+    /// The closure receives a scratch copy of the cached device state. If it returns `Ok`, the copy is sent to the driver
+    /// and becomes the cached state. If the closure or the driver update fails, the cached state is left untouched.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, ButtonState, Error};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// vjoy.transaction(1, |device| {
+    ///     device.set_button(1, ButtonState::Pressed)?;
+    ///     device.set_axis(1, 0x4000)?;
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn transaction<T, F>(&mut self, device_id: u32, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Device) -> Result<T, Error>,
+    {
+        let mut scratch = self.get_device_state(device_id)?;
+        let value = f(&mut scratch)?;
+        self.update_device_state(&scratch)?;
+
+        Ok(value)
+    }
 
-        // 4 fields á 32 buttons as single bits
-        let mut button_field_data = [0; 4];
-        for (i, field) in button_field_data.iter_mut().enumerate() {
-            let start = i * 32;
-            let end = i * 32 + 32;
+    /// Resets all components of a device via the driver and updates the cached state accordingly.
+    ///
+    /// The driver centers the X, Y and Z axes and sets all other axes to 0. Pending changes of the cached state are discarded.
+    #[profiling::function]
+    pub fn reset_device(&mut self, device_id: u32) -> Result<(), Error> {
+        let index = self.device_index(device_id)?;
+        Self::reset_device_ffi(&self.ffi, device_id)?;
 
-            let buttons = &button_data[start..end];
-            for (bit, state) in buttons.iter().enumerate().take(32) {
-                if *state == ButtonState::Pressed {
-                    *field |= 0x1 << bit;
-                }
+        Self::reset_to_driver_defaults(&mut self.devices[index]);
+        Self::record_state(&mut self.recorder, &self.devices[index]);
+        self.committed[index] = Some(self.devices[index].clone());
+
+        Ok(())
+    }
+
+    /// Resets all devices via the driver. See [reset_device](Self::reset_device).
+    #[profiling::function]
+    pub fn reset_all_devices(&mut self) {
+        unsafe {
+            self.ffi.ResetAll();
+        }
+
+        for (device, committed) in self.devices.iter_mut().zip(&mut self.committed) {
+            Self::reset_to_driver_defaults(device);
+            Self::record_state(&mut self.recorder, device);
+            *committed = Some(device.clone());
+        }
+    }
+
+    /// Releases all buttons of a device via the driver. Other pending changes of the cached state are kept.
+    #[profiling::function]
+    pub fn reset_buttons(&mut self, device_id: u32) -> Result<(), Error> {
+        let index = self.device_index(device_id)?;
+        unsafe {
+            if self.ffi.ResetButtons(device_id) != 1 {
+                let device_state = Self::get_device_ffi_status(&self.ffi, device_id);
+                return Err(Error::Ffi(FFIError::ButtonsCouldNotBeReset(
+                    device_id,
+                    device_state,
+                )));
             }
         }
 
-        let hats_data: Vec<u32> = (0..4)
-            .map(|index| {
-                if let Some(hat) = device.hats.get(index) {
-                    match hat.get() {
-                        HatState::Continuous(c) => c,
-                        HatState::Discrete(d) => d as u32,
-                    }
-                } else {
-                    0
-                }
-            })
-            .collect();
+        let committed = self.committed[index].iter_mut();
+        for device in std::iter::once(&mut self.devices[index]).chain(committed) {
+            device.buttons.iter_mut().for_each(|button| button.reset());
+        }
+        if let Some(committed) = &self.committed[index] {
+            Self::record_state(&mut self.recorder, committed);
+        }
 
-        let mut data = JOYSTICK_POSITION {
-            bDevice: device.id as u8,
+        Ok(())
+    }
 
-            wAxisX: axis_data[0],
-            wAxisY: axis_data[1],
-            wAxisZ: axis_data[2],
+    /// Centers all hats of a device via the driver. Other pending changes of the cached state are kept.
+    #[profiling::function]
+    pub fn reset_hats(&mut self, device_id: u32) -> Result<(), Error> {
+        let index = self.device_index(device_id)?;
+        unsafe {
+            if self.ffi.ResetPovs(device_id) != 1 {
+                let device_state = Self::get_device_ffi_status(&self.ffi, device_id);
+                return Err(Error::Ffi(FFIError::HatsCouldNotBeReset(
+                    device_id,
+                    device_state,
+                )));
+            }
+        }
 
-            wAxisXRot: axis_data[3],
-            wAxisYRot: axis_data[4],
-            wAxisZRot: axis_data[5],
+        let committed = self.committed[index].iter_mut();
+        for device in std::iter::once(&mut self.devices[index]).chain(committed) {
+            device.hats.iter_mut().for_each(|hat| hat.reset());
+        }
+        if let Some(committed) = &self.committed[index] {
+            Self::record_state(&mut self.recorder, committed);
+        }
 
-            wDial: axis_data[6],
-            wSlider: axis_data[7],
+        Ok(())
+    }
 
-            wWheel: axis_data[8],
-            wAccelerator: axis_data[9],
-            wBrake: axis_data[10],
-            wClutch: axis_data[11],
+    /// Changes of the cached device state that were not sent to the driver yet.
+    ///
+    /// Before the first update, all components are part of the diff.
+    #[profiling::function]
+    pub fn pending_diff(&self, device_id: u32) -> Result<DeviceDiff, Error> {
+        let index = self.device_index(device_id)?;
+        let device = &self.devices[index];
+        let diff = match &self.committed[index] {
+            Some(committed) => device.diff(committed),
+            None => device.diff(&Device::default()),
+        };
 
-            wSteering: axis_data[12],
-            wAileron: axis_data[13],
-            wRudder: axis_data[14],
-            wThrottle: axis_data[15],
+        Ok(diff)
+    }
 
-            wAxisVX: 0,
-            wAxisVY: 0,
-            wAxisVZ: 0,
-            wAxisVBRX: 0,
-            wAxisVBRY: 0,
-            wAxisVBRZ: 0,
+    /// Captures the cached states of all devices, including changes that were not sent to the driver yet.
+    #[profiling::function]
+    pub fn snapshot(&self) -> VJoySnapshot {
+        VJoySnapshot {
+            devices: self.devices.clone(),
+        }
+    }
 
-            lButtons: button_field_data[0],
-            lButtonsEx1: button_field_data[1],
-            lButtonsEx2: button_field_data[2],
-            lButtonsEx3: button_field_data[3],
+    /// Applies the device states of a snapshot and sends them to the driver.
+    ///
+    /// Only the devices of the snapshot are sent. Devices of this instance that are missing from the snapshot keep their state.
+    /// Nothing is applied if a device of the snapshot is not owned by this instance or its buttons, axes or hats differ,
+    /// e.g. after the device was reconfigured in vJoyConf.
+    #[profiling::function]
+    pub fn restore(&mut self, snapshot: &VJoySnapshot) -> Result<(), Error> {
+        for saved in &snapshot.devices {
+            self.capabilities(saved.id)?
+                .validate(saved)
+                .map_err(|_| Error::App(AppError::SnapshotMismatch(saved.id)))?;
+        }
 
-            bHats: hats_data[0],
-            bHatsEx1: hats_data[1],
-            bHatsEx2: hats_data[2],
-            bHatsEx3: hats_data[3],
-        };
+        for saved in &snapshot.devices {
+            self.update_device_state(saved)?;
+        }
 
-        Self::update_device_data(&self.ffi, device.id, &mut data)?;
+        Ok(())
+    }
+
+    /// Registers a callback for force feedback packets sent to any device owned by this instance.
+    ///
+    /// The callback receives the device ID and the parsed packet. It runs on the driver's FFB thread, so keep it short.
+    /// Panics inside the callback are caught and logged, and packets that fail to parse are dropped with a warning.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, Error};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// vjoy.on_ffb(|device_id, packet| {
+    ///     println!("Device {device_id}: {packet}");
+    /// });
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn on_ffb<F>(&mut self, callback: F)
+    where
+        F: FnMut(u32, &FfbPacket) + Send + 'static,
+    {
+        let mut dispatcher = self
+            .ffb_dispatcher()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        dispatcher.on_packet(callback);
+    }
+
+    /// Registers a callback for every force feedback packet the driver sends, before it is parsed.
+    ///
+    /// Unlike [on_ffb](Self::on_ffb), this includes malformed packets and packets for devices owned by other feeders.
+    /// Panics inside the callback are caught and logged.
+    #[profiling::function]
+    pub fn on_ffb_raw<F>(&mut self, callback: F)
+    where
+        F: FnMut(&RawFfbPacket) + Send + 'static,
+    {
+        let mut dispatcher = self
+            .ffb_dispatcher()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        dispatcher.on_raw_packet(callback);
+    }
+
+    /// Records every force feedback packet the driver sends to a session file at `path` until this instance is dropped.
+    ///
+    /// Load the file via [FfbSession::load](crate::ffb::FfbSession::load) to replay it offline. Write errors are logged.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, Error};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// vjoy.record_ffb("capture.ffb")?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn record_ffb<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let mut recorder = FfbRecorder::create(path)?;
+        self.on_ffb_raw(move |packet| {
+            if let Err(e) = recorder.record(packet) {
+                error!("Failed to record FFB packet: {}", e);
+            }
+        });
 
         Ok(())
     }
 
+    /// Records every device state sent to the driver from now on, including resets, until [stop_recording](Self::stop_recording).
+    ///
+    /// The last sent state of each device is recorded first, so the recording starts from the current driver state.
+    /// Load the file via [Recording::load](crate::Recording::load). Write errors are logged and do not fail the update.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, Error, Recorder};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// vjoy.start_recording(Recorder::create("flight.rec")?.rotate(10 * 1024 * 1024, 4));
+    /// // ...
+    /// vjoy.stop_recording();
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn start_recording(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+        for device in self.committed.iter().flatten() {
+            Self::record_state(&mut self.recorder, device);
+        }
+    }
+
+    /// Stops recording and returns the flushed recorder.
+    #[profiling::function]
+    pub fn stop_recording(&mut self) -> Option<Recorder> {
+        let mut recorder = self.recorder.take()?;
+        if let Err(e) = recorder.flush() {
+            error!("Failed to flush recording: {}", e);
+        }
+
+        Some(recorder)
+    }
+
+    /// Returns a channel that receives force feedback packets sent to any device owned by this instance.
+    ///
+    /// Multiple receivers each get every packet. Dropped receivers are unregistered automatically.
+    #[profiling::function]
+    pub fn ffb_receiver(&mut self) -> Receiver<FfbReport> {
+        self.ffb_dispatcher()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .receiver()
+    }
+
     /// All vJoy devices share the same guid and vendor/device information.
     ///
     /// To differentiate between vJoy devices from other libraries (e.g. SDL2), you may use the configuration instead.
+    #[deprecated(note = "use find_device with a DeviceQuery instead")]
     #[profiling::function]
     pub fn get_id_for_configuration(
         &self,
@@ -352,6 +490,93 @@ impl VJoy {
         }
     }
 
+    /// All configured devices that match the query, best match first. See [DeviceQuery::rank].
+    ///
+    /// Devices owned by this instance use their cached capabilities, the layout of all other devices is read from the driver.
+    #[profiling::function]
+    pub fn find_devices(&self, query: &DeviceQuery) -> Vec<DeviceMatch> {
+        let candidates = (1..=16).filter_map(|device_id| {
+            if let Ok(capabilities) = self.capabilities(device_id) {
+                return Some((capabilities.clone(), DeviceStatus::Owned));
+            }
+
+            let status = DeviceStatus::from(Self::get_device_ffi_status(&self.ffi, device_id));
+            if !query.allows_status(status) {
+                return None;
+            }
+            match self.device_builder(device_id).build() {
+                Ok(device) => Some((device.capabilities(), status)),
+                Err(e) => {
+                    trace!("Skipped device {} in query: {}", device_id, e);
+                    None
+                }
+            }
+        });
+
+        query.rank(candidates)
+    }
+
+    /// ID of the best device match for the query.
+    #[profiling::function]
+    pub fn find_device(&self, query: &DeviceQuery) -> Result<u32, Error> {
+        match self.find_devices(query).first() {
+            Some(device) => Ok(device.device_id()),
+            None => Err(Error::App(AppError::NoDeviceMatches(query.to_string()))),
+        }
+    }
+
+    /// Names a device by a query, e.g. `"throttle"`, so it can be looked up via [device](Self::device) regardless of its ID.
+    ///
+    /// Aliases resolve against the devices owned by this instance, each to a different device. See [DeviceAliases].
+    /// Registering an existing name replaces its query.
+    ///
+    /// ```no_run
+    /// # use vjoy::{VJoy, AxisKind, ButtonState, DeviceQuery, Error};
+    /// # let mut vjoy = VJoy::from_default_dll_location()?;
+    /// vjoy.register_alias("throttle", DeviceQuery::new().axes(&[AxisKind::Z, AxisKind::Slider]));
+    ///
+    /// vjoy.device_mut("throttle")?.set_button(1, ButtonState::Pressed)?;
+    /// vjoy.update_all_devices()?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[profiling::function]
+    pub fn register_alias<S: Into<String>>(&mut self, name: S, query: DeviceQuery) {
+        self.aliases.register(name, query);
+        self.aliases.resolve(&self.capabilities);
+    }
+
+    #[profiling::function]
+    pub fn unregister_alias(&mut self, name: &str) -> Option<DeviceQuery> {
+        let query = self.aliases.unregister(name)?;
+        self.aliases.resolve(&self.capabilities);
+
+        Some(query)
+    }
+
+    #[profiling::function]
+    pub fn aliases(&self) -> &DeviceAliases {
+        &self.aliases
+    }
+
+    /// Device ID of a registered alias.
+    #[profiling::function]
+    pub fn alias_id(&self, name: &str) -> Result<u32, Error> {
+        self.aliases.device_id(name)
+    }
+
+    /// Cached state of the device behind an alias.
+    #[profiling::function]
+    pub fn device(&self, name: &str) -> Result<&Device, Error> {
+        let device_id = self.alias_id(name)?;
+        self.get_device_state_ref(device_id)
+    }
+
+    #[profiling::function]
+    pub fn device_mut(&mut self, name: &str) -> Result<&mut Device, Error> {
+        let device_id = self.alias_id(name)?;
+        self.get_device_state_mut(device_id)
+    }
+
     #[profiling::function]
     fn new(path: &str) -> Result<Self, Error> {
         unsafe {
@@ -364,75 +589,150 @@ impl VJoy {
             Ok(Self {
                 ffi,
                 devices: Vec::new(),
+                committed: Vec::new(),
+                capabilities: Vec::new(),
+                ffb: None,
+                aliases: DeviceAliases::new(),
+                reset_on_drop: false,
+                recorder: None,
             })
         }
     }
 
+    /// Lazily registers the FFB trampoline with the driver on first use.
     #[profiling::function]
-    fn fetch_devices(&mut self) {
+    fn ffb_dispatcher(&mut self) -> &Mutex<FfbDispatcher> {
+        if self.ffb.is_none() {
+            // The driver gives no guarantee that no callback still reads `user_data` after unregistering,
+            // so the dispatcher is leaked and only emptied on drop
+            let dispatcher: &'static Mutex<FfbDispatcher> = Box::leak(Box::new(Mutex::new(
+                FfbDispatcher::new(self.devices.iter().map(|device| device.id)),
+            )));
+
+            unsafe {
+                let user_data = dispatcher as *const Mutex<FfbDispatcher> as PVOID;
+                // The bindings declare the callback as "C" on 64-bit and "stdcall" on 32-bit targets, both of which "system" matches
+                let callback = std::mem::transmute::<
+                    Option<unsafe extern "system" fn(PVOID, PVOID)>,
+                    vjoy_sys::FfbGenCB,
+                >(Some(ffb_trampoline));
+                self.ffi.FfbRegisterGenCB(callback, user_data);
+            }
+            trace!("Registered FFB callback");
+
+            self.ffb = Some(dispatcher);
+        }
+
+        self.ffb.expect("dispatcher was registered above")
+    }
+
+    /// Position of an owned device in `devices`, `committed` and `capabilities`. Devices that could not be acquired leave gaps in the IDs.
+    #[profiling::function]
+    fn device_index(&self, device_id: u32) -> Result<usize, Error> {
+        self.capabilities
+            .iter()
+            .position(|capabilities| capabilities.device_id() == device_id)
+            .ok_or(Error::App(AppError::DeviceNotFound(device_id)))
+    }
+
+    #[profiling::function]
+    fn fetch_devices(&mut self, reset: bool) {
         for device_id in 1..=16 {
             if self.acquire_device(device_id).is_ok() {
-                let button_count = unsafe { self.ffi.GetVJDButtonNumber(device_id) } as u32;
-                let buttons: Vec<Button> = (1..=button_count)
-                    .map(|button_id| Button {
-                        id: button_id as u8,
-                        state: ButtonState::Released,
-                    })
-                    .collect();
-                trace!("Device {} button count: {}", device_id, buttons.len());
-
-                let mut axes = Vec::new();
-                for axis_id in 1..=8 {
-                    let axis_index = (axis_id - 1) as usize;
-                    let axis_display_name = AXES_DISPLAY_NAMES[axis_index].to_string();
-                    let axis_hid_usage = AXES_HID_USAGE[axis_index];
-                    let exists = unsafe { self.ffi.GetVJDAxisExist(device_id, axis_hid_usage) };
-                    trace!(
-                        "Device {} axis id: {} display name: {} hid usage: {}",
-                        device_id, axis_id, axis_display_name, axis_hid_usage
-                    );
-                    if exists == 1 {
-                        let axis = Axis {
-                            display_name: axis_display_name,
-                            hid_usage: axis_hid_usage,
-                            id: axis_id,
-                            value: 0,
-                        };
-                        axes.push(axis);
+                let mut device = match self.device_builder(device_id).build() {
+                    Ok(device) => device,
+                    Err(e) => {
+                        error!("Skipped device {}: {}", device_id, e);
+                        self.relinquish_device(device_id);
+                        continue;
+                    }
+                };
+
+                let mut committed = None;
+                if reset {
+                    match Self::reset_device_ffi(&self.ffi, device_id) {
+                        Ok(()) => {
+                            Self::reset_to_driver_defaults(&mut device);
+                            committed = Some(device.clone());
+                        }
+                        Err(e) => error!("Failed to reset device {}: {}", device_id, e),
                     }
                 }
 
-                let hat_disc_count = unsafe { self.ffi.GetVJDDiscPovNumber(device_id) } as u32;
-                let hat_cont_count = unsafe { self.ffi.GetVJDContPovNumber(device_id) } as u32;
-
-                let hats = if hat_disc_count > 0 {
-                    (1..=hat_disc_count)
-                        .map(|hat_id| Hat {
-                            id: hat_id as u8,
-                            state: HatState::Discrete(FourWayHat::Centered),
-                        })
-                        .collect()
-                } else if hat_cont_count > 0 {
-                    (1..=hat_cont_count)
-                        .map(|hat_id| Hat {
-                            id: hat_id as u8,
-                            state: HatState::Continuous(u32::MAX),
-                        })
-                        .collect()
-                } else {
-                    Vec::new()
-                };
+                self.capabilities.push(device.capabilities());
+                self.devices.push(device);
+                self.committed.push(committed);
+            }
+        }
+    }
 
-                trace!("Device {} hat switch count: {}", device_id, hats.len());
+    /// Reads the layout of a device from the driver. This does not require the device to be acquired.
+    #[profiling::function]
+    fn device_builder(&self, device_id: u32) -> DeviceBuilder {
+        let button_count = unsafe { self.ffi.GetVJDButtonNumber(device_id) } as u32;
+        trace!("Device {} button count: {}", device_id, button_count);
+        let mut builder = DeviceBuilder::new(device_id).buttons(button_count);
+
+        for kind in AxisKind::ALL {
+            let exists = unsafe { self.ffi.GetVJDAxisExist(device_id, kind.hid_usage()) };
+            trace!(
+                "Device {} axis id: {} display name: {} hid usage: {}",
+                device_id,
+                kind.id(),
+                kind.display_name(),
+                kind.hid_usage()
+            );
+            if exists == 1 {
+                builder = builder.axis(kind);
+            }
+        }
 
-                self.devices.push(Device {
-                    id: device_id,
-                    buttons,
-                    axes,
-                    hats,
-                })
+        let hat_disc_count = unsafe { self.ffi.GetVJDDiscPovNumber(device_id) } as usize;
+        let hat_cont_count = unsafe { self.ffi.GetVJDContPovNumber(device_id) } as usize;
+
+        // vJoyConf only offers one hat type per device, but the driver reports both counts separately
+        let hat_disc_count = hat_disc_count.min(DeviceBuilder::MAX_HATS);
+        let hat_cont_count = hat_cont_count.min(DeviceBuilder::MAX_HATS - hat_disc_count);
+        builder = builder
+            .hats(hat_disc_count, HatType::Discrete)
+            .hats(hat_cont_count, HatType::Continuous);
+        trace!(
+            "Device {} hat switch count: {}",
+            device_id,
+            hat_disc_count + hat_cont_count
+        );
+
+        builder
+    }
+
+    #[profiling::function]
+    fn reset_device_ffi(ffi: &vjoy_sys::vJoyInterface, device_id: u32) -> Result<(), Error> {
+        unsafe {
+            if ffi.ResetVJD(device_id) != 1 {
+                let device_state = Self::get_device_ffi_status(ffi, device_id);
+                return Err(Error::Ffi(FFIError::DeviceCouldNotBeReset(
+                    device_id,
+                    device_state,
+                )));
             }
         }
+        Ok(())
+    }
+
+    #[profiling::function]
+    fn record_state(recorder: &mut Option<Recorder>, device: &Device) {
+        if let Some(recorder) = recorder
+            && let Err(e) = recorder.record(device)
+        {
+            error!("Failed to record device {}: {}", device.id, e);
+        }
+    }
+
+    /// Mirrors the values the driver sets on reset: X, Y and Z centered, all other axes at 0, buttons released and hats centered.
+    #[profiling::function]
+    fn reset_to_driver_defaults(device: &mut Device) {
+        device.reset_to_driver_defaults();
+        device.dirty = false;
     }
 
     #[profiling::function]
@@ -458,6 +758,91 @@ impl VJoy {
         }
     }
 
+    /// Encodes a device state into the driver's position struct.
+    ///
+    /// Components the device lacks are encoded as neutral: axes at their mid-point, buttons released, hats centered.
+    #[profiling::function]
+    fn joystick_position(device: &Device) -> JOYSTICK_POSITION {
+        // Axes value or default mid-point
+        let axis_data: Vec<i32> = (0..16)
+            .map(|index| {
+                if let Some(axis) = device.axes.get(index) {
+                    axis.get()
+                } else {
+                    16384
+                }
+            })
+            .collect();
+
+        let button_data: Vec<ButtonState> = (0..128)
+            .map(|index| {
+                if let Some(button) = device.buttons.get(index) {
+                    button.get()
+                } else {
+                    ButtonState::Released
+                }
+            })
+            .collect();
+
+        // 4 fields á 32 buttons as single bits
+        let mut button_field_data = [0; 4];
+        for (i, field) in button_field_data.iter_mut().enumerate() {
+            let start = i * 32;
+            let end = i * 32 + 32;
+
+            let buttons = &button_data[start..end];
+            for (bit, state) in buttons.iter().enumerate().take(32) {
+                if *state == ButtonState::Pressed {
+                    *field |= 0x1 << bit;
+                }
+            }
+        }
+
+        let hats_data = device.raw_hats();
+
+        JOYSTICK_POSITION {
+            bDevice: device.id as u8,
+
+            wAxisX: axis_data[0],
+            wAxisY: axis_data[1],
+            wAxisZ: axis_data[2],
+
+            wAxisXRot: axis_data[3],
+            wAxisYRot: axis_data[4],
+            wAxisZRot: axis_data[5],
+
+            wDial: axis_data[6],
+            wSlider: axis_data[7],
+
+            wWheel: axis_data[8],
+            wAccelerator: axis_data[9],
+            wBrake: axis_data[10],
+            wClutch: axis_data[11],
+
+            wSteering: axis_data[12],
+            wAileron: axis_data[13],
+            wRudder: axis_data[14],
+            wThrottle: axis_data[15],
+
+            wAxisVX: 0,
+            wAxisVY: 0,
+            wAxisVZ: 0,
+            wAxisVBRX: 0,
+            wAxisVBRY: 0,
+            wAxisVBRZ: 0,
+
+            lButtons: button_field_data[0],
+            lButtonsEx1: button_field_data[1],
+            lButtonsEx2: button_field_data[2],
+            lButtonsEx3: button_field_data[3],
+
+            bHats: hats_data[0],
+            bHatsEx1: hats_data[1],
+            bHatsEx2: hats_data[2],
+            bHatsEx3: hats_data[3],
+        }
+    }
+
     #[profiling::function]
     fn update_device_data(
         ffi: &vjoy_sys::vJoyInterface,
@@ -552,10 +937,31 @@ impl VJoy {
     }
 }
 
+impl DeviceSink for VJoy {
+    /// Sends the state via [update_device_state](Self::update_device_state).
+    #[profiling::function]
+    fn send(&mut self, device: &Device) -> Result<(), Error> {
+        self.update_device_state(device)
+    }
+}
+
 impl Drop for VJoy {
     #[profiling::function]
     fn drop(&mut self) {
+        if let Some(dispatcher) = self.ffb {
+            unsafe {
+                self.ffi.FfbRegisterGenCB(None, std::ptr::null_mut());
+            }
+            // Releases callbacks, recorders and channels. Late callbacks find no owned devices and drop their packets
+            *dispatcher.lock().unwrap_or_else(|e| e.into_inner()) = FfbDispatcher::default();
+        }
+
         for device in &self.devices {
+            if self.reset_on_drop
+                && let Err(e) = Self::reset_device_ffi(&self.ffi, device.id)
+            {
+                error!("Failed to reset device {}: {}", device.id, e);
+            }
             self.relinquish_device(device.id);
         }
     }
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use vjoy::{
+        AppError, AxisKind, DeviceAliases, DeviceCapabilities, DeviceQuery, Error, HatType,
+    };
+
+    fn owned() -> Vec<DeviceCapabilities> {
+        vec![
+            DeviceCapabilities::new(
+                1,
+                32,
+                vec![AxisKind::X, AxisKind::Y, AxisKind::Rz],
+                vec![HatType::Discrete],
+            ),
+            DeviceCapabilities::new(2, 16, vec![AxisKind::Z, AxisKind::Slider], vec![]),
+            DeviceCapabilities::new(3, 8, vec![AxisKind::X, AxisKind::Y, AxisKind::Rz], vec![]),
+        ]
+    }
+
+    #[test]
+    fn resolve_aliases() {
+        let mut aliases = DeviceAliases::new();
+        aliases.register(
+            "stick",
+            DeviceQuery::new()
+                .axes(&[AxisKind::X, AxisKind::Y])
+                .hats(1..),
+        );
+        aliases.register("throttle", DeviceQuery::new().axis(AxisKind::Slider));
+        aliases.register("pedals", DeviceQuery::new().axis(AxisKind::Rz));
+        aliases.resolve(&owned());
+
+        assert_eq!(aliases.len(), 3);
+        assert_eq!(aliases.device_id("stick").unwrap(), 1);
+        assert_eq!(aliases.device_id("throttle").unwrap(), 2);
+        // Device 1 fits better but is claimed by "stick"
+        assert_eq!(aliases.device_id("pedals").unwrap(), 3);
+        assert_eq!(
+            aliases.get("pedals").unwrap().to_string(),
+            "pedals -> Device 3"
+        );
+
+        aliases.unregister("stick").unwrap();
+        aliases.register("pedals", DeviceQuery::new().axis(AxisKind::Rz).hats(1..));
+        aliases.resolve(&owned());
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases.device_id("pedals").unwrap(), 1);
+    }
+
+    #[test]
+    fn name_alias_in_errors() {
+        let mut aliases = DeviceAliases::new();
+        aliases.register(
+            "wheel",
+            DeviceQuery::new().axes(&[AxisKind::X, AxisKind::Dial]),
+        );
+        aliases.resolve(&owned());
+
+        assert!(matches!(
+            aliases.device_id("wheel"),
+            Err(Error::App(AppError::AliasNotMatched(name, _))) if name == "wheel"
+        ));
+        assert!(
+            aliases
+                .device_id("wheel")
+                .unwrap_err()
+                .to_string()
+                .contains("\"wheel\"")
+        );
+        assert!(matches!(
+            aliases.device_id("yoke"),
+            Err(Error::App(AppError::AliasNotFound(name))) if name == "yoke"
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use vjoy::{AppError, AxisKind, Device, DeviceCapabilities, Error, HatType};
+
+    fn wheel() -> DeviceCapabilities {
+        DeviceCapabilities::new(
+            2,
+            32,
+            vec![AxisKind::Slider, AxisKind::X, AxisKind::Z, AxisKind::X],
+            vec![HatType::Continuous, HatType::Continuous],
+        )
+    }
+
+    #[test]
+    fn axis_kinds() {
+        for (index, kind) in AxisKind::ALL.iter().enumerate() {
+            assert_eq!(kind.id(), index as u32 + 1);
+            assert_eq!(AxisKind::from_id(kind.id()), Some(*kind));
+            assert_eq!(AxisKind::from_hid_usage(kind.hid_usage()), Some(*kind));
+        }
+        assert_eq!(AxisKind::Rz.hid_usage(), 0x35);
+        assert_eq!(AxisKind::from_id(0), None);
+        assert_eq!(AxisKind::from_hid_usage(0x38), None);
+        assert_eq!(AxisKind::Dial.to_string(), "Dial/Slider2");
+    }
+
+    #[test]
+    fn describe_capabilities() {
+        let wheel = wheel();
+        assert_eq!(wheel.axes(), &[AxisKind::X, AxisKind::Z, AxisKind::Slider]);
+        assert!(wheel.has_axis(AxisKind::Z));
+        assert!(!wheel.has_axis(AxisKind::Y));
+        assert_eq!(
+            wheel.to_string(),
+            "Device ID: 2 | buttons: 32 | axes: [X, Z, Slider] | hats: [continuous, continuous]"
+        );
+        assert_eq!(
+            Device::default().capabilities(),
+            DeviceCapabilities::default()
+        );
+    }
+
+    #[test]
+    fn check_requirements() {
+        let wheel = wheel();
+        let profile = DeviceCapabilities::new(0, 12, vec![AxisKind::X], vec![HatType::Continuous]);
+        assert!(wheel.satisfies(&profile).is_ok());
+        assert!(wheel.satisfies(&wheel).is_ok());
+
+        let too_many_buttons = DeviceCapabilities::new(0, 64, vec![], vec![]);
+        let missing_axis = DeviceCapabilities::new(0, 0, vec![AxisKind::Rx], vec![]);
+        let wrong_hat = DeviceCapabilities::new(0, 0, vec![], vec![HatType::Discrete]);
+        for required in [too_many_buttons, missing_axis, wrong_hat] {
+            assert!(matches!(
+                wheel.satisfies(&required),
+                Err(Error::App(AppError::CapabilityMismatch(2, _)))
+            ));
+        }
+
+        assert!(wheel.validate(&Device::default()).is_err());
+        assert!(
+            DeviceCapabilities::default()
+                .validate(&Device::default())
+                .is_ok()
+        );
+    }
+}
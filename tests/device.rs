@@ -0,0 +1,340 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::{
+        AppError, Axis, AxisKind, AxisMerge, ButtonMerge, ButtonState, CrossfadeScheduler, Device,
+        DeviceBuilder, DeviceCapabilities, Error, FourWayHat, HatMerge, HatState, HatType,
+        MergePolicy, MergeSource,
+    };
+
+    fn profiles() -> (Device, Device) {
+        let from = DeviceBuilder::new(1)
+            .buttons(2)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+
+        let mut to = from.clone();
+        to.set_button(1, ButtonState::Pressed).unwrap();
+        to.set_axis(1, 0x7FFF).unwrap();
+        to.set_axis(2, -100).unwrap();
+        to.set_hat(1, HatState::Discrete(FourWayHat::East)).unwrap();
+        to.set_hat(2, HatState::Continuous(1000)).unwrap();
+
+        let mut from = from;
+        from.set_hat(2, HatState::Continuous(35000)).unwrap();
+
+        (from, to)
+    }
+
+    #[test]
+    fn build_device() {
+        let device = DeviceBuilder::new(3)
+            .buttons(8)
+            .axes(&[AxisKind::Rz, AxisKind::X])
+            .axis(AxisKind::X)
+            .hats(2, HatType::Continuous)
+            .build()
+            .unwrap();
+
+        assert_eq!(device.id(), 3);
+        assert_eq!(device.num_buttons(), 8);
+        assert!(
+            device
+                .buttons()
+                .all(|button| button.get() == ButtonState::Released)
+        );
+        let axes: Vec<_> = device.axes().map(|axis| axis.kind()).collect();
+        assert_eq!(axes, vec![Some(AxisKind::X), Some(AxisKind::Rz)]);
+        assert!(device.axes().all(|axis| axis.get() == 0));
+        assert!(
+            device
+                .hats()
+                .all(|hat| hat.get() == HatState::Continuous(u32::MAX))
+        );
+        assert!(device.is_dirty());
+
+        let capabilities = device.capabilities();
+        assert!(capabilities.validate(&device).is_ok());
+        assert_eq!(
+            DeviceBuilder::from_capabilities(&capabilities)
+                .build()
+                .unwrap(),
+            device
+        );
+    }
+
+    #[test]
+    fn hat_types() {
+        let mut device = DeviceBuilder::new(1)
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+        assert_eq!(device.hat_type(), HatType::Discrete);
+
+        assert!(matches!(
+            device.set_hat(1, HatState::Continuous(9000)),
+            Err(Error::App(AppError::HatTypeMismatch(
+                1,
+                1,
+                HatType::Discrete,
+                HatState::Continuous(9000)
+            )))
+        ));
+        assert!(matches!(
+            device.set_hat(2, HatState::Discrete(FourWayHat::East)),
+            Err(Error::App(AppError::HatTypeMismatch(
+                1,
+                2,
+                HatType::Continuous,
+                _
+            )))
+        ));
+
+        // Centered and absent hats are both sent as u32::MAX
+        assert_eq!(device.raw_hats(), [u32::MAX; 4]);
+        device
+            .set_hat(1, HatState::Discrete(FourWayHat::East))
+            .unwrap();
+        device.set_hat(2, HatState::Continuous(9000)).unwrap();
+        assert_eq!(device.raw_hats(), [1, 9000, u32::MAX, u32::MAX]);
+
+        // Unvalidated states that don't match the hat type are centered
+        device
+            .hats_mut()
+            .next()
+            .unwrap()
+            .set(HatState::Continuous(4500));
+        assert_eq!(device.raw_hats(), [u32::MAX, 9000, u32::MAX, u32::MAX]);
+    }
+
+    #[test]
+    fn axis_range() {
+        assert_eq!(Axis::MAX - Axis::CENTER, Axis::CENTER - Axis::MIN);
+        assert_eq!(Axis::from_normalized(1.0), Axis::MAX);
+        assert_eq!(Axis::from_normalized(-1.0), Axis::MIN);
+        assert_eq!(Axis::from_normalized(0.0), Axis::CENTER);
+        assert_eq!(Axis::from_normalized(2.0), Axis::MAX);
+
+        let mut device = DeviceBuilder::new(1).axis(AxisKind::X).build().unwrap();
+        let axis = device.axes_mut().next().unwrap();
+        axis.set(Axis::MAX);
+        assert_eq!(axis.normalized(), 1.0);
+        axis.set_normalized(-0.5);
+        assert_eq!(axis.get(), 0x2000);
+        assert_eq!(axis.normalized(), -0.5);
+    }
+
+    #[test]
+    fn reject_invalid_layouts() {
+        for builder in [
+            DeviceBuilder::new(0),
+            DeviceBuilder::new(17),
+            DeviceBuilder::new(1).buttons(129),
+            DeviceBuilder::new(1).hats(5, HatType::Discrete),
+        ] {
+            assert!(matches!(
+                builder.build(),
+                Err(Error::App(AppError::InvalidDeviceLayout(_, _)))
+            ));
+        }
+
+        let profile = DeviceCapabilities::new(1, 4, vec![AxisKind::Y], vec![]);
+        let device = DeviceBuilder::new(1).buttons(4).build().unwrap();
+        assert!(device.capabilities().satisfies(&profile).is_err());
+    }
+
+    #[test]
+    fn diff_devices() {
+        let previous = DeviceBuilder::new(1)
+            .buttons(4)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .build()
+            .unwrap();
+        let mut device = previous.clone();
+        assert!(device.diff(&previous).is_empty());
+
+        device.set_button(2, ButtonState::Pressed).unwrap();
+        device.set_axis(2, 100).unwrap();
+        device
+            .set_hat(1, HatState::Discrete(FourWayHat::South))
+            .unwrap();
+        device.set_button(3, ButtonState::Released).unwrap();
+
+        let diff = device.diff(&previous);
+        assert_eq!(diff.device_id, 1);
+        assert_eq!(diff.buttons, vec![(2, ButtonState::Pressed)]);
+        assert_eq!(diff.axes, vec![(2, 100)]);
+        assert_eq!(diff.hats, vec![(1, HatState::Discrete(FourWayHat::South))]);
+        assert_eq!(diff.len(), 3);
+
+        let mut replayed = previous.clone();
+        diff.apply(&mut replayed).unwrap();
+        assert_eq!(replayed, device);
+
+        // Axes are identified by position, not by their kind
+        let previous = DeviceBuilder::new(1)
+            .axes(&[AxisKind::X, AxisKind::Rz])
+            .build()
+            .unwrap();
+        let mut device = previous.clone();
+        device.set_axis(2, 0x6000).unwrap();
+        let diff = device.diff(&previous);
+        assert_eq!(diff.axes, vec![(2, 0x6000)]);
+        let mut replayed = previous.clone();
+        diff.apply(&mut replayed).unwrap();
+        assert_eq!(replayed, device);
+    }
+
+    #[test]
+    fn lerp_devices() {
+        let (from, to) = profiles();
+
+        let quarter = Device::lerp(&from, &to, 0.25).unwrap();
+        let axes: Vec<i32> = quarter.axes().map(|axis| axis.get()).collect();
+        assert_eq!(axes, vec![0x2000, -25]);
+        assert_eq!(
+            quarter.buttons().next().unwrap().get(),
+            ButtonState::Released
+        );
+        let hats: Vec<HatState> = quarter.hats().map(|hat| hat.get()).collect();
+        // 350° to 10° along the shorter arc
+        assert_eq!(
+            hats,
+            vec![
+                HatState::Discrete(FourWayHat::Centered),
+                HatState::Continuous(35500)
+            ]
+        );
+
+        let switched = Device::lerp_with_threshold(&from, &to, 0.25, 0.2).unwrap();
+        assert_eq!(
+            switched.buttons().next().unwrap().get(),
+            ButtonState::Pressed
+        );
+        assert_eq!(
+            switched.hats().next().unwrap().get(),
+            HatState::Discrete(FourWayHat::East)
+        );
+
+        assert_eq!(Device::lerp(&from, &to, 2.0).unwrap(), to);
+        assert_eq!(Device::lerp(&from, &to, 0.0).unwrap(), from);
+
+        let other = DeviceBuilder::new(1).buttons(2).build().unwrap();
+        assert!(matches!(
+            Device::lerp(&from, &other, 0.5),
+            Err(Error::App(AppError::CapabilityMismatch(1, _)))
+        ));
+    }
+
+    #[test]
+    fn crossfade_device() {
+        let (from, to) = profiles();
+        let start = Duration::from_secs(1);
+
+        let mut scheduler = CrossfadeScheduler::new();
+        scheduler
+            .start(&from, to.clone(), start, Duration::from_millis(100))
+            .unwrap();
+        assert!(scheduler.is_active(1));
+
+        let mut devices = [from.clone()];
+        scheduler
+            .update(devices.iter_mut(), start + Duration::from_millis(50))
+            .unwrap();
+        assert_eq!(devices[0], Device::lerp(&from, &to, 0.5).unwrap());
+        assert!(devices[0].is_dirty());
+        assert!(scheduler.is_active(1));
+
+        scheduler
+            .update(devices.iter_mut(), start + Duration::from_millis(150))
+            .unwrap();
+        assert_eq!(devices[0], to);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn merge_sources() {
+        let neutral = DeviceBuilder::new(1)
+            .buttons(2)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .build()
+            .unwrap();
+
+        let mut hotas = neutral.clone();
+        hotas.set_button(1, ButtonState::Pressed).unwrap();
+        hotas.set_axis(1, 0x6000).unwrap();
+        hotas.set_axis(2, 0x4000).unwrap();
+        hotas
+            .set_hat(1, HatState::Discrete(FourWayHat::North))
+            .unwrap();
+
+        let mut keyboard = neutral.clone();
+        keyboard.set_button(2, ButtonState::Pressed).unwrap();
+        keyboard.set_axis(1, 0x7000).unwrap();
+        keyboard.set_axis(2, 0x1000).unwrap();
+        keyboard
+            .set_hat(1, HatState::Discrete(FourWayHat::West))
+            .unwrap();
+
+        let mut network = neutral.clone();
+        network.set_axis(1, 0x4000).unwrap();
+        network.set_axis(2, 0x4000).unwrap();
+
+        let sources = [
+            MergeSource::new(&hotas).updated(Duration::from_millis(20)),
+            MergeSource::new(&keyboard).updated(Duration::from_millis(10)),
+            MergeSource::new(&network).priority(1),
+        ];
+        let buttons = |device: &Device| device.buttons().map(|b| b.get()).collect::<Vec<_>>();
+        let axes = |device: &Device| device.axes().map(|a| a.get()).collect::<Vec<_>>();
+        let hat = |device: &Device| device.hats().next().unwrap().get();
+
+        let mut merged = neutral.clone();
+        merged.merge(&sources, MergePolicy::default()).unwrap();
+        assert_eq!(buttons(&merged), vec![ButtonState::Pressed; 2]);
+        assert_eq!(axes(&merged), vec![0x7000, 0x1000]);
+        // The network source is centered and falls through to the earlier of the remaining sources
+        assert_eq!(hat(&merged), HatState::Discrete(FourWayHat::North));
+
+        let policy = |axes| MergePolicy {
+            buttons: ButtonMerge::Priority,
+            axes,
+            hats: HatMerge::MostRecent,
+        };
+        merged.merge(&sources, policy(AxisMerge::SumClamp)).unwrap();
+        assert_eq!(buttons(&merged), vec![ButtonState::Released; 2]);
+        assert_eq!(axes(&merged), vec![0x8000, 0x1000]);
+        assert_eq!(hat(&merged), HatState::Discrete(FourWayHat::North));
+
+        merged.merge(&sources, policy(AxisMerge::Average)).unwrap();
+        assert_eq!(axes(&merged), vec![0x5AAB, 0x3000]);
+        merged.merge(&sources, policy(AxisMerge::Priority)).unwrap();
+        assert_eq!(axes(&merged), vec![0x4000, 0x4000]);
+
+        let other = DeviceBuilder::new(1).buttons(2).build().unwrap();
+        assert!(matches!(
+            merged.merge(&[MergeSource::new(&other)], MergePolicy::default()),
+            Err(Error::App(AppError::CapabilityMismatch(1, _)))
+        ));
+
+        // Sources are usually other devices with the same layout
+        let mut pedals = DeviceBuilder::new(2)
+            .buttons(2)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .build()
+            .unwrap();
+        pedals.set_axis(2, 0x2000).unwrap();
+        merged
+            .merge(&[MergeSource::new(&pedals)], MergePolicy::default())
+            .unwrap();
+        assert_eq!(merged.id(), 1);
+        assert_eq!(axes(&merged), vec![0, 0x2000]);
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use vjoy::{ButtonState, DPad, DPadButtons, FourWayHat, HatAngle, HatState, SocdPolicy};
+
+    const RELEASED: DPadButtons = DPadButtons {
+        up: ButtonState::Released,
+        down: ButtonState::Released,
+        left: ButtonState::Released,
+        right: ButtonState::Released,
+    };
+
+    fn press_left_then_right(policy: SocdPolicy) -> DPad {
+        let mut dpad = DPad::new(policy);
+        dpad.update(DPadButtons {
+            left: ButtonState::Pressed,
+            ..RELEASED
+        });
+        dpad.update(DPadButtons {
+            left: ButtonState::Pressed,
+            right: ButtonState::Pressed,
+            ..RELEASED
+        });
+        dpad
+    }
+
+    #[test]
+    fn socd_policies() {
+        assert_eq!(
+            press_left_then_right(SocdPolicy::Neutral).four_way(),
+            FourWayHat::Centered
+        );
+        assert_eq!(
+            press_left_then_right(SocdPolicy::LastInputWins).four_way(),
+            FourWayHat::East
+        );
+        assert_eq!(
+            press_left_then_right(SocdPolicy::FirstInputWins).four_way(),
+            FourWayHat::West
+        );
+        assert_eq!(
+            press_left_then_right(SocdPolicy::UpPriority).four_way(),
+            FourWayHat::Centered
+        );
+
+        let mut dpad = DPad::new(SocdPolicy::UpPriority);
+        dpad.update(DPadButtons {
+            down: ButtonState::Pressed,
+            ..RELEASED
+        });
+        dpad.update(DPadButtons {
+            up: ButtonState::Pressed,
+            down: ButtonState::Pressed,
+            ..RELEASED
+        });
+        assert_eq!(dpad.four_way(), FourWayHat::North);
+    }
+
+    #[test]
+    fn diagonals() {
+        let mut dpad = DPad::new(SocdPolicy::Neutral);
+        dpad.update(DPadButtons {
+            up: ButtonState::Pressed,
+            ..RELEASED
+        });
+        dpad.update(DPadButtons {
+            up: ButtonState::Pressed,
+            right: ButtonState::Pressed,
+            ..RELEASED
+        });
+        assert_eq!(dpad.angle().map(|a| a.hundredths()), Some(4500));
+        assert_eq!(dpad.four_way(), FourWayHat::East);
+    }
+
+    #[test]
+    fn buttons_from_hat_state() {
+        assert_eq!(
+            DPadButtons::from_hat_state(HatState::Discrete(FourWayHat::Centered)),
+            RELEASED
+        );
+        assert_eq!(
+            DPadButtons::from_hat_state(HatState::Discrete(FourWayHat::West)),
+            DPadButtons {
+                left: ButtonState::Pressed,
+                ..RELEASED
+            }
+        );
+        assert_eq!(
+            DPadButtons::from_hat_state(HatState::Continuous(22500)),
+            DPadButtons {
+                down: ButtonState::Pressed,
+                left: ButtonState::Pressed,
+                ..RELEASED
+            }
+        );
+        assert_eq!(
+            DPadButtons::from_hat_state(HatAngle::from_degrees(350.0).unwrap().into()),
+            DPadButtons {
+                up: ButtonState::Pressed,
+                ..RELEASED
+            }
+        );
+    }
+}
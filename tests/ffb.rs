@@ -0,0 +1,620 @@
+#[cfg(test)]
+mod tests {
+    // Synthetic packets built by hand after the vJoy FFB report layouts. Most target device 1, some device 2.
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use vjoy::ffb::{
+        AxisMotion, BlockLoad, BlockLoadStatus, ConditionReport, ConstantReport, DeviceControl,
+        EffectOperation, EffectOperationReport, EffectReport, EffectType, EnvelopeReport,
+        FfbDispatcher, FfbEffectManager, FfbForce, FfbPacket, FfbSession, FfbSynthesizer,
+        IOCTL_HID_SET_FEATURE, IOCTL_HID_WRITE_REPORT, MAX_EFFECT_BLOCK_INDEX, PeriodicReport,
+        RampReport, RawFfbPacket, Rumble, RumbleMixer, RumbleMotor, RumbleRule, RumbleSource,
+        SynthesizerConfig,
+    };
+    use vjoy::{AppError, Axis, AxisKind, DeviceBuilder, Error, FfbError};
+
+    fn write(data: &[u8]) -> RawFfbPacket {
+        RawFfbPacket::new(IOCTL_HID_WRITE_REPORT, data.to_vec())
+    }
+
+    #[test]
+    fn parse_effect_parameters() {
+        let effect = write(&[
+            0x11, 0x01, 0x04, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x07,
+            0x00, 0x40, 0x00, 0x00,
+        ]);
+        assert_eq!(effect.device_id().unwrap(), 1);
+        assert_eq!(
+            effect.parse().unwrap(),
+            FfbPacket::Effect(EffectReport {
+                block_index: 1,
+                effect_type: EffectType::Sine,
+                duration: 0xFFFF,
+                trigger_repeat: 0,
+                sample_period: 0,
+                start_delay: 0,
+                gain: 0xFF,
+                trigger_button: 0xFF,
+                axes_enabled: 0x03,
+                polar: true,
+                direction_x: 0x4000,
+                direction_y: 0,
+            })
+        );
+
+        let envelope = write(&[
+            0x12, 0x01, 0xE8, 0x03, 0x00, 0x00, 0xC8, 0x00, 0x00, 0x00, 0x2C, 0x01, 0x00, 0x00,
+        ]);
+        assert_eq!(
+            envelope.parse().unwrap(),
+            FfbPacket::Envelope(EnvelopeReport {
+                block_index: 1,
+                attack_level: 1000,
+                fade_level: 0,
+                attack_time: 200,
+                fade_time: 300,
+            })
+        );
+
+        let condition = write(&[
+            0x13, 0x02, 0x01, 0x00, 0x00, 0x10, 0x27, 0xF0, 0xD8, 0x10, 0x27, 0x10, 0x27, 0x64,
+            0x00,
+        ]);
+        assert_eq!(
+            condition.parse().unwrap(),
+            FfbPacket::Condition(ConditionReport {
+                block_index: 2,
+                is_y: true,
+                center_point_offset: 0,
+                positive_coefficient: 10000,
+                negative_coefficient: -10000,
+                positive_saturation: 10000,
+                negative_saturation: 10000,
+                dead_band: 100,
+            })
+        );
+
+        let periodic = write(&[
+            0x14, 0x01, 0x88, 0x13, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00,
+        ]);
+        assert_eq!(
+            periodic.parse().unwrap(),
+            FfbPacket::Periodic(PeriodicReport {
+                block_index: 1,
+                magnitude: 5000,
+                offset: 0,
+                phase: 0,
+                period: 100,
+            })
+        );
+
+        let constant = write(&[0x15, 0x03, 0x18, 0xFC]);
+        assert_eq!(
+            constant.parse().unwrap(),
+            FfbPacket::Constant(ConstantReport {
+                block_index: 3,
+                magnitude: -1000,
+            })
+        );
+
+        let ramp = write(&[0x16, 0x04, 0x30, 0xF8, 0xD0, 0x07]);
+        assert_eq!(
+            ramp.parse().unwrap(),
+            FfbPacket::Ramp(RampReport {
+                block_index: 4,
+                start: -2000,
+                end: 2000,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_control_reports() {
+        assert_eq!(
+            write(&[0x1A, 0x01, 0x02, 0x01]).parse().unwrap(),
+            FfbPacket::EffectOperation(EffectOperationReport {
+                block_index: 1,
+                operation: EffectOperation::Solo,
+                loop_count: 1,
+            })
+        );
+        assert_eq!(
+            write(&[0x1B, 0x05]).parse().unwrap(),
+            FfbPacket::BlockFree(5)
+        );
+        assert_eq!(
+            write(&[0x1C, 0x04]).parse().unwrap(),
+            FfbPacket::DeviceControl(DeviceControl::Reset)
+        );
+        assert_eq!(
+            write(&[0x2D, 0x80]).parse().unwrap(),
+            FfbPacket::DeviceGain(0x80)
+        );
+
+        let new_effect = RawFfbPacket::new(IOCTL_HID_SET_FEATURE, vec![0x21, 0x08, 0x00, 0x00]);
+        assert_eq!(new_effect.device_id().unwrap(), 2);
+        assert_eq!(new_effect.report_id().unwrap(), 0x11);
+        assert_eq!(
+            new_effect.parse().unwrap(),
+            FfbPacket::CreateNewEffect(EffectType::Spring)
+        );
+    }
+
+    #[test]
+    fn reject_malformed_packets() {
+        assert!(matches!(
+            write(&[0x15, 0x03, 0x18]).parse(),
+            Err(Error::Ffb(_))
+        ));
+        assert!(matches!(write(&[]).parse(), Err(Error::Ffb(_))));
+        assert!(matches!(
+            write(&[0x1A, 0x01, 0x09, 0x01]).parse(),
+            Err(Error::Ffb(_))
+        ));
+        assert!(matches!(write(&[0x18, 0x01]).parse(), Err(Error::Ffb(_))));
+        assert!(matches!(
+            write(&[0x05, 0x01, 0x00, 0x00]).device_id(),
+            Err(Error::Ffb(_))
+        ));
+        assert!(matches!(
+            RawFfbPacket::new(0, vec![0x15, 0x03, 0x18, 0xFC]).parse(),
+            Err(Error::Ffb(_))
+        ));
+    }
+
+    #[test]
+    fn dispatch_packets() {
+        let mut dispatcher = FfbDispatcher::new([1, 3]);
+        let parsed = Arc::new(Mutex::new(Vec::new()));
+        let raw = Arc::new(Mutex::new(0));
+
+        for _ in 0..2 {
+            let parsed = parsed.clone();
+            dispatcher.on_packet(move |device_id, packet| {
+                parsed.lock().unwrap().push((device_id, *packet));
+            });
+        }
+        let counter = raw.clone();
+        dispatcher.on_raw_packet(move |_| *counter.lock().unwrap() += 1);
+        dispatcher.on_packet(|_, _| panic!("callback panics are caught"));
+        let first = dispatcher.receiver();
+        let second = dispatcher.receiver();
+
+        // Fan-out to every callback and channel
+        dispatcher.dispatch(&write(&[0x1D, 0x80]));
+        assert_eq!(
+            *parsed.lock().unwrap(),
+            vec![
+                (1, FfbPacket::DeviceGain(0x80)),
+                (1, FfbPacket::DeviceGain(0x80))
+            ]
+        );
+        for receiver in [&first, &second] {
+            let report = receiver.try_recv().unwrap();
+            assert_eq!(
+                (report.device_id, report.packet),
+                (1, FfbPacket::DeviceGain(0x80))
+            );
+        }
+
+        // Packets for other devices and unsupported reports only reach raw callbacks
+        dispatcher.dispatch(&write(&[0x2D, 0x80]));
+        dispatcher.dispatch(&write(&[0x17, 0x01]));
+        assert_eq!(parsed.lock().unwrap().len(), 2);
+        assert!(first.try_recv().is_err());
+        assert_eq!(*raw.lock().unwrap(), 3);
+
+        // Dropped receivers are unregistered on the next packet
+        drop(first);
+        assert_eq!(dispatcher.num_receivers(), 2);
+        dispatcher.dispatch(&write(&[0x3D, 0x40]));
+        assert_eq!(dispatcher.num_receivers(), 1);
+        assert_eq!(second.try_recv().unwrap().device_id, 3);
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn sine(block_index: u8, duration: u16) -> FfbPacket {
+        FfbPacket::Effect(EffectReport {
+            block_index,
+            effect_type: EffectType::Sine,
+            duration,
+            trigger_repeat: 0,
+            sample_period: 0,
+            start_delay: 0,
+            gain: 0xFF,
+            trigger_button: 0xFF,
+            axes_enabled: 0x01,
+            polar: false,
+            direction_x: 0,
+            direction_y: 0,
+        })
+    }
+
+    fn operation(block_index: u8, operation: EffectOperation, loop_count: u8) -> FfbPacket {
+        FfbPacket::EffectOperation(EffectOperationReport {
+            block_index,
+            operation,
+            loop_count,
+        })
+    }
+
+    #[test]
+    fn manager_allocates_block_indices() {
+        let mut manager = FfbEffectManager::new(1);
+        let load = manager.apply(&FfbPacket::CreateNewEffect(EffectType::Sine), ms(0));
+        assert_eq!(
+            load,
+            Some(BlockLoad {
+                block_index: 1,
+                status: BlockLoadStatus::Success
+            })
+        );
+        assert_eq!(manager.create_effect(EffectType::Spring).block_index, 2);
+
+        manager.apply(&FfbPacket::BlockFree(1), ms(0));
+        assert_eq!(manager.next_free_index(), Some(1));
+
+        for _ in 0..MAX_EFFECT_BLOCK_INDEX - 1 {
+            manager.create_effect(EffectType::Constant);
+        }
+        assert_eq!(
+            manager.create_effect(EffectType::Constant).status,
+            BlockLoadStatus::Full
+        );
+
+        manager.apply(&FfbPacket::DeviceControl(DeviceControl::Reset), ms(0));
+        assert_eq!(manager.effects().count(), 0);
+    }
+
+    #[test]
+    fn manager_tracks_playback() {
+        let mut manager = FfbEffectManager::new(1);
+        manager.apply(&FfbPacket::CreateNewEffect(EffectType::Sine), ms(0));
+        manager.apply(&sine(1, 100), ms(0));
+        manager.apply(&FfbPacket::CreateNewEffect(EffectType::Sine), ms(0));
+        manager.apply(&sine(2, 0xFFFF), ms(0));
+
+        manager.apply(&operation(1, EffectOperation::Start, 2), ms(1000));
+        manager.apply(&operation(2, EffectOperation::Start, 1), ms(1000));
+        assert_eq!(manager.active_effects(ms(1050)).len(), 2);
+        assert_eq!(
+            manager.effect(1).unwrap().playback_time(ms(1150)),
+            Some(ms(50))
+        );
+        assert_eq!(manager.effect(1).unwrap().playback_time(ms(1200)), None);
+
+        manager.apply(&operation(1, EffectOperation::Solo, 1), ms(2000));
+        let active: Vec<u8> = manager
+            .active_effects(ms(2010))
+            .iter()
+            .map(|(effect, _)| effect.block_index())
+            .collect();
+        assert_eq!(active, vec![1]);
+
+        manager.apply(&FfbPacket::DeviceControl(DeviceControl::Pause), ms(2050));
+        assert_eq!(manager.active_effects(ms(5000))[0].1, ms(50));
+        manager.apply(&FfbPacket::DeviceControl(DeviceControl::Continue), ms(5000));
+        assert_eq!(manager.active_effects(ms(5020))[0].1, ms(70));
+
+        manager.apply(&FfbPacket::DeviceGain(0x40), ms(5020));
+        assert_eq!(manager.gain(), 0x40);
+        manager.apply(
+            &FfbPacket::DeviceControl(DeviceControl::StopAllEffects),
+            ms(5030),
+        );
+        assert!(manager.active_effects(ms(5030)).is_empty());
+    }
+
+    fn playing(manager: &mut FfbEffectManager, packets: &[FfbPacket]) {
+        manager.apply(&FfbPacket::CreateNewEffect(EffectType::Constant), ms(0));
+        for packet in packets {
+            manager.apply(packet, ms(0));
+        }
+        manager.apply(&operation(1, EffectOperation::Start, 1), ms(0));
+    }
+
+    fn effect(effect_type: EffectType, duration: u16, axes_enabled: u8) -> FfbPacket {
+        FfbPacket::Effect(EffectReport {
+            block_index: 1,
+            effect_type,
+            duration,
+            trigger_repeat: 0,
+            sample_period: 0,
+            start_delay: 0,
+            gain: 0xFF,
+            trigger_button: 0xFF,
+            axes_enabled,
+            polar: false,
+            direction_x: 0,
+            direction_y: 0,
+        })
+    }
+
+    #[test]
+    fn synthesize_forces() {
+        let synthesizer = FfbSynthesizer::new(SynthesizerConfig::default());
+        let at_rest = [AxisMotion::default(); 2];
+
+        let mut manager = FfbEffectManager::new(1);
+        playing(
+            &mut manager,
+            &[
+                effect(EffectType::Constant, 1000, 0x01),
+                FfbPacket::Constant(ConstantReport {
+                    block_index: 1,
+                    magnitude: -5000,
+                }),
+                FfbPacket::Envelope(EnvelopeReport {
+                    block_index: 1,
+                    attack_level: 0,
+                    fade_level: 0,
+                    attack_time: 100,
+                    fade_time: 0,
+                }),
+            ],
+        );
+        assert_eq!(synthesizer.force(&manager, &at_rest, ms(0)).x, 0.0);
+        assert_eq!(synthesizer.force(&manager, &at_rest, ms(50)).x, -0.25);
+        assert_eq!(
+            synthesizer.force(&manager, &at_rest, ms(500)),
+            FfbForce { x: -0.5, y: 0.0 }
+        );
+        manager.apply(&FfbPacket::DeviceGain(0), ms(500));
+        assert_eq!(synthesizer.force(&manager, &at_rest, ms(500)).x, 0.0);
+
+        let mut manager = FfbEffectManager::new(1);
+        playing(
+            &mut manager,
+            &[
+                effect(EffectType::Square, 0xFFFF, 0x01),
+                FfbPacket::Periodic(PeriodicReport {
+                    block_index: 1,
+                    magnitude: 8000,
+                    offset: 1000,
+                    phase: 0,
+                    period: 100,
+                }),
+            ],
+        );
+        assert_eq!(synthesizer.force(&manager, &at_rest, ms(1020)).x, 0.9);
+        assert_eq!(synthesizer.force(&manager, &at_rest, ms(1070)).x, -0.7);
+
+        let mut manager = FfbEffectManager::new(1);
+        playing(
+            &mut manager,
+            &[
+                effect(EffectType::Ramp, 1000, 0x02),
+                FfbPacket::Ramp(RampReport {
+                    block_index: 1,
+                    start: -10000,
+                    end: 10000,
+                }),
+            ],
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &at_rest, ms(750)),
+            FfbForce { x: 0.0, y: 0.5 }
+        );
+    }
+
+    #[test]
+    fn track_axes_by_kind() {
+        let mut wheel = DeviceBuilder::new(1)
+            .axes(&[AxisKind::X, AxisKind::Rz])
+            .build()
+            .unwrap();
+        wheel.set_axis(1, Axis::CENTER).unwrap();
+        wheel.set_axis(2, Axis::MAX).unwrap();
+
+        let mut synthesizer = FfbSynthesizer::new(SynthesizerConfig {
+            x_axis: AxisKind::Rz,
+            y_axis: None,
+            ..Default::default()
+        });
+        let motion = synthesizer.track(&wheel, ms(0)).unwrap();
+        assert_eq!(motion[0].position, 1.0);
+        assert_eq!(motion[1].position, 0.0);
+
+        let mut synthesizer = FfbSynthesizer::new(SynthesizerConfig::default());
+        assert!(matches!(
+            synthesizer.track(&wheel, ms(0)),
+            Err(Error::App(AppError::AxisNotFound(1, 2)))
+        ));
+    }
+
+    #[test]
+    fn synthesize_conditions() {
+        let synthesizer = FfbSynthesizer::new(SynthesizerConfig::default());
+        let condition = |block_index| ConditionReport {
+            block_index,
+            is_y: false,
+            center_point_offset: 0,
+            positive_coefficient: 10000,
+            negative_coefficient: 5000,
+            positive_saturation: 4000,
+            negative_saturation: 0,
+            dead_band: 1000,
+        };
+        let motion = |position, velocity| {
+            [
+                AxisMotion {
+                    position,
+                    velocity,
+                    acceleration: 0.0,
+                },
+                AxisMotion::default(),
+            ]
+        };
+
+        let mut manager = FfbEffectManager::new(1);
+        playing(
+            &mut manager,
+            &[
+                effect(EffectType::Spring, 0xFFFF, 0x01),
+                FfbPacket::Condition(condition(1)),
+            ],
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.05, 0.0), ms(10)).x,
+            0.0
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.3, 0.0), ms(10)).x,
+            -0.2
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.9, 0.0), ms(10)).x,
+            -0.4
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(-0.5, 0.0), ms(10)).x,
+            0.2
+        );
+
+        let mut manager = FfbEffectManager::new(1);
+        playing(
+            &mut manager,
+            &[
+                effect(EffectType::Friction, 0xFFFF, 0x01),
+                FfbPacket::Condition(condition(1)),
+            ],
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.5, 0.0), ms(10)).x,
+            0.0
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.5, 2.0), ms(10)).x,
+            -0.4
+        );
+        assert_eq!(
+            synthesizer.force(&manager, &motion(0.5, -2.0), ms(10)).x,
+            0.5
+        );
+    }
+
+    #[test]
+    fn replay_session() {
+        let capture = "\
+# vjoy ffb session v1
+# spring on the wheel, then a short rumble
+0 000b0191 1108
+1000 000b000f 110108ffff000000000000ffff0100000000
+1500 000b000f 130100000010271027000000000000
+
+2000 000b000f 1a010101
+2100 000b000f 1701
+250000 000b000f 1d80
+";
+        let session = FfbSession::read_from(capture.as_bytes()).unwrap();
+        assert_eq!(session.packets.len(), 6);
+        assert_eq!(
+            session.packets[2].timestamp,
+            ms(1) + Duration::from_micros(500)
+        );
+
+        let mut written = Vec::new();
+        session.write_to(&mut written).unwrap();
+        assert_eq!(FfbSession::read_from(written.as_slice()).unwrap(), session);
+
+        let mut manager = FfbEffectManager::new(1);
+        let mut replayer = session.replayer();
+        assert_eq!(replayer.advance(&mut manager, ms(1)), 2);
+        assert_eq!(manager.effect(1).unwrap().effect_type(), EffectType::Spring);
+        assert_eq!(
+            replayer.next_timestamp(),
+            Some(ms(1) + Duration::from_micros(500))
+        );
+        // The unsupported report 0x17 is skipped
+        assert_eq!(replayer.advance(&mut manager, ms(100)), 2);
+        assert_eq!(replayer.skipped(), 1);
+        assert_eq!(manager.active_effects(ms(100)).len(), 1);
+        assert!(!replayer.is_finished());
+
+        let mut manager = FfbEffectManager::new(1);
+        assert_eq!(session.replay(&mut manager), 5);
+        assert_eq!(manager.gain(), 0x80);
+
+        let broken = "# vjoy ffb session v1\n0 000b000f 1d8\n";
+        assert!(matches!(
+            FfbSession::read_from(broken.as_bytes()),
+            Err(Error::Ffb(FfbError::InvalidSessionLine(2, _)))
+        ));
+        let headless = "0 000b000f 1d80\n";
+        assert!(matches!(
+            FfbSession::read_from(headless.as_bytes()),
+            Err(Error::Ffb(FfbError::InvalidSessionLine(1, _)))
+        ));
+    }
+
+    #[test]
+    fn downmix_rumble() {
+        let periodic = |block_index, magnitude, period| {
+            FfbPacket::Periodic(PeriodicReport {
+                block_index,
+                magnitude,
+                offset: 0,
+                phase: 0,
+                period,
+            })
+        };
+
+        let mut manager = FfbEffectManager::new(1);
+        for (block_index, packet) in [
+            (1, periodic(1, 4000, 100)),
+            (2, periodic(2, 3000, 10)),
+            (
+                3,
+                FfbPacket::Constant(ConstantReport {
+                    block_index: 3,
+                    magnitude: -8000,
+                }),
+            ),
+        ] {
+            let effect_type = match packet {
+                FfbPacket::Constant(_) => EffectType::Constant,
+                _ => EffectType::Sine,
+            };
+            manager.apply(&FfbPacket::CreateNewEffect(effect_type), ms(0));
+            if let FfbPacket::Effect(report) = effect(effect_type, 0xFFFF, 0x01) {
+                manager.apply(
+                    &FfbPacket::Effect(EffectReport {
+                        block_index,
+                        ..report
+                    }),
+                    ms(0),
+                );
+            }
+            manager.apply(&packet, ms(0));
+            manager.apply(&operation(block_index, EffectOperation::Start, 1), ms(0));
+        }
+
+        let mut received = Vec::new();
+        RumbleMixer::default().update(&manager, ms(10), &mut |rumble: Rumble| {
+            received.push(rumble)
+        });
+        assert_eq!(
+            received,
+            vec![Rumble {
+                low_frequency: 1.0,
+                high_frequency: 0.3
+            }]
+        );
+
+        let mixer = RumbleMixer::new(vec![RumbleRule {
+            source: RumbleSource::Periodic {
+                min_frequency: 5.0,
+                max_frequency: 50.0,
+            },
+            motor: RumbleMotor::HighFrequency,
+            gain: 0.5,
+        }]);
+        manager.apply(&FfbPacket::DeviceGain(0x80), ms(10));
+        let rumble = mixer.mix(&manager, ms(10));
+        assert_eq!(rumble.low_frequency, 0.0);
+        assert!((rumble.high_frequency - 0.1).abs() < 0.001);
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::{
+        ButtonState, DeviceBuilder, DeviceSink, Gesture, GestureClassifier, GestureConfig,
+        GestureMapping, MemorySink,
+    };
+
+    const MAPPING: GestureMapping = GestureMapping {
+        short_press: 1,
+        long_press: Some(2),
+        double_tap: Some(3),
+        triple_tap: Some(4),
+    };
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn feed(classifier: &mut GestureClassifier, input: &[(u64, ButtonState)]) -> Vec<Gesture> {
+        input
+            .iter()
+            .filter_map(|(t, state)| classifier.update(*state, ms(*t)))
+            .map(|event| event.gesture)
+            .collect()
+    }
+
+    #[test]
+    fn short_press_after_tap_window() {
+        let mut classifier = GestureClassifier::new(MAPPING, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[
+                (0, ButtonState::Pressed),
+                (100, ButtonState::Released),
+                (300, ButtonState::Released),
+                (400, ButtonState::Released),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::ShortPress]);
+        assert!(classifier.outputs().contains(&(1, ButtonState::Pressed)));
+
+        classifier.update(ButtonState::Released, ms(1000));
+        assert!(
+            classifier
+                .outputs()
+                .iter()
+                .all(|(_, state)| *state == ButtonState::Released)
+        );
+    }
+
+    #[test]
+    fn long_press_holds_until_release() {
+        let mut classifier = GestureClassifier::new(MAPPING, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[
+                (0, ButtonState::Pressed),
+                (499, ButtonState::Pressed),
+                (500, ButtonState::Pressed),
+                (2000, ButtonState::Pressed),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::LongPress]);
+        assert!(classifier.outputs().contains(&(2, ButtonState::Pressed)));
+
+        assert_eq!(classifier.update(ButtonState::Released, ms(2100)), None);
+        assert!(classifier.outputs().contains(&(2, ButtonState::Released)));
+    }
+
+    #[test]
+    fn double_and_triple_tap() {
+        let mut classifier = GestureClassifier::new(MAPPING, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[
+                (0, ButtonState::Pressed),
+                (50, ButtonState::Released),
+                (150, ButtonState::Pressed),
+                (200, ButtonState::Released),
+                (600, ButtonState::Released),
+                (1000, ButtonState::Pressed),
+                (1050, ButtonState::Released),
+                (1100, ButtonState::Pressed),
+                (1150, ButtonState::Released),
+                (1200, ButtonState::Pressed),
+                (1250, ButtonState::Released),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::DoubleTap, Gesture::TripleTap]);
+    }
+
+    #[test]
+    fn unmapped_taps_emit_immediately() {
+        let mapping = GestureMapping {
+            short_press: 7,
+            long_press: None,
+            double_tap: None,
+            triple_tap: None,
+        };
+        let mut classifier = GestureClassifier::new(mapping, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[(0, ButtonState::Pressed), (5000, ButtonState::Released)],
+        );
+        assert_eq!(gestures, vec![Gesture::ShortPress]);
+        assert_eq!(classifier.outputs(), vec![(7, ButtonState::Pressed)]);
+    }
+
+    #[test]
+    fn unmapped_double_tap_emits_short_press() {
+        let mapping = GestureMapping {
+            short_press: 1,
+            long_press: None,
+            double_tap: None,
+            triple_tap: Some(4),
+        };
+        let mut classifier = GestureClassifier::new(mapping, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[
+                (0, ButtonState::Pressed),
+                (50, ButtonState::Released),
+                (150, ButtonState::Pressed),
+                (200, ButtonState::Released),
+                (600, ButtonState::Released),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::ShortPress]);
+        assert!(classifier.outputs().contains(&(1, ButtonState::Pressed)));
+    }
+
+    #[test]
+    fn long_press_discards_taps() {
+        let mut classifier = GestureClassifier::new(MAPPING, GestureConfig::default());
+        let gestures = feed(
+            &mut classifier,
+            &[
+                (0, ButtonState::Pressed),
+                (50, ButtonState::Released),
+                (150, ButtonState::Pressed),
+                (650, ButtonState::Pressed),
+                (700, ButtonState::Released),
+                (1000, ButtonState::Released),
+            ],
+        );
+        assert_eq!(gestures, vec![Gesture::LongPress]);
+    }
+
+    #[test]
+    fn apply_only_changed_buttons() {
+        let mut sink = MemorySink::new();
+        sink.send(&DeviceBuilder::new(1).buttons(4).build().unwrap())
+            .unwrap();
+        let mut device = sink.device(1).unwrap().clone();
+
+        let mut classifier = GestureClassifier::new(MAPPING, GestureConfig::default());
+        classifier.update(ButtonState::Released, ms(0));
+        classifier.apply(&mut device).unwrap();
+        assert!(!device.is_dirty());
+
+        classifier.update(ButtonState::Pressed, ms(10));
+        classifier.update(ButtonState::Pressed, ms(510));
+        classifier.apply(&mut device).unwrap();
+        assert!(device.is_dirty());
+        assert_eq!(device.buttons().nth(1).unwrap().get(), ButtonState::Pressed);
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use vjoy::{
+        AppError, DeviceBuilder, DeviceSink, EightWayHat, Error, FourWayHat, Hat, HatAngle,
+        HatState, HatType, MemorySink,
+    };
+
+    #[test]
+    fn hat_angle_constructors() {
+        assert_eq!(HatAngle::from_degrees(90.0).unwrap(), HatAngle::EAST);
+        assert_eq!(HatAngle::from_degrees(359.999).unwrap(), HatAngle::NORTH);
+        assert_eq!(
+            HatAngle::from_radians(std::f32::consts::PI).unwrap(),
+            HatAngle::SOUTH
+        );
+        assert_eq!(HatAngle::from_hundredths(4500).unwrap().degrees(), 45.0);
+
+        assert!(HatAngle::from_degrees(360.0).is_err());
+        assert!(HatAngle::from_degrees(-1.0).is_err());
+        assert!(HatAngle::from_degrees(f32::NAN).is_err());
+        assert!(HatAngle::from_hundredths(u32::MAX).is_err());
+        assert_eq!(HatState::Continuous(u32::MAX).angle(), None);
+    }
+
+    #[test]
+    fn hat_angle_from_vector() {
+        assert_eq!(HatAngle::from_vector(0.0, 1.0), Some(HatAngle::NORTH));
+        assert_eq!(HatAngle::from_vector(1.0, 0.0), Some(HatAngle::EAST));
+        assert_eq!(HatAngle::from_vector(0.0, -1.0), Some(HatAngle::SOUTH));
+        assert_eq!(HatAngle::from_vector(-1.0, 0.0), Some(HatAngle::WEST));
+        assert_eq!(
+            HatAngle::from_vector(1.0, 1.0).map(|a| a.hundredths()),
+            Some(4500)
+        );
+        assert_eq!(HatAngle::from_vector(0.0, 0.0), None);
+
+        assert_eq!(
+            FourWayHat::nearest(HatAngle::from_degrees(44.0).unwrap()),
+            FourWayHat::North
+        );
+        assert_eq!(
+            FourWayHat::nearest(HatAngle::from_degrees(46.0).unwrap()),
+            FourWayHat::East
+        );
+        assert_eq!(
+            FourWayHat::nearest(HatAngle::from_degrees(350.0).unwrap()),
+            FourWayHat::North
+        );
+    }
+
+    #[test]
+    fn hat_type_centered() {
+        assert_eq!(
+            HatType::Discrete.centered(),
+            HatState::Discrete(FourWayHat::Centered)
+        );
+        assert_eq!(
+            HatType::Continuous.centered(),
+            HatState::Continuous(u32::MAX)
+        );
+        assert_eq!(HatState::Continuous(9000).hat_type(), HatType::Continuous);
+    }
+
+    #[test]
+    fn eight_way_hat() {
+        assert_eq!(
+            HatState::from(EightWayHat::NorthEast),
+            HatState::Continuous(4500)
+        );
+        assert_eq!(
+            HatState::from(EightWayHat::NorthWest),
+            HatState::Continuous(31500)
+        );
+        assert_eq!(
+            HatState::from(EightWayHat::Centered),
+            HatState::Continuous(u32::MAX)
+        );
+
+        assert_eq!(EightWayHat::from_continuous(2249), EightWayHat::North);
+        assert_eq!(EightWayHat::from_continuous(2250), EightWayHat::NorthEast);
+        assert_eq!(EightWayHat::from_continuous(33750), EightWayHat::North);
+        assert_eq!(EightWayHat::from_continuous(21000), EightWayHat::SouthWest);
+        assert_eq!(
+            EightWayHat::from_continuous(u32::MAX),
+            EightWayHat::Centered
+        );
+
+        let device = DeviceBuilder::new(1)
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+        let mut hats: Vec<Hat> = device.hats().copied().collect();
+        hats[1].set_eight_way(EightWayHat::SouthWest);
+        assert_eq!(hats[1].eight_way(), Some(EightWayHat::SouthWest));
+        hats[0].set_eight_way(EightWayHat::NorthEast);
+        assert_eq!(hats[0].get(), HatState::Discrete(FourWayHat::East));
+        assert_eq!(hats[0].eight_way(), None);
+        hats[1].set_eight_way(EightWayHat::Centered);
+        assert_eq!(hats[1].get(), HatState::Continuous(u32::MAX));
+    }
+
+    #[test]
+    fn device_hat_from_vector() {
+        let mut sink = MemorySink::new();
+        let device = DeviceBuilder::new(1)
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+        sink.send(&device).unwrap();
+        let mut device = sink.device(1).unwrap().clone();
+
+        device.set_hat_from_vector(1, 0.9, 0.2, 0.1).unwrap();
+        device.set_hat_from_vector(2, -1.0, 0.0, 0.1).unwrap();
+        assert!(device.is_dirty());
+        let hats: Vec<HatState> = device.hats().map(|hat| hat.get()).collect();
+        assert_eq!(
+            hats,
+            vec![
+                HatState::Discrete(FourWayHat::East),
+                HatState::Continuous(27000)
+            ]
+        );
+
+        device.set_hat_from_vector(2, 0.05, 0.0, 0.1).unwrap();
+        assert_eq!(
+            device.hats().nth(1).unwrap().get(),
+            HatState::Continuous(u32::MAX)
+        );
+        assert!(matches!(
+            device.set_hat_from_vector(3, 1.0, 0.0, 0.1),
+            Err(Error::App(AppError::HatNotFound(1, 3)))
+        ));
+    }
+}
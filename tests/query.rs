@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod tests {
+    use vjoy::{AxisKind, DeviceCapabilities, DeviceQuery, DeviceStatus, HatType};
+
+    fn candidates() -> Vec<(DeviceCapabilities, DeviceStatus)> {
+        let gamepad = DeviceCapabilities::new(
+            1,
+            16,
+            vec![AxisKind::X, AxisKind::Y, AxisKind::Rx, AxisKind::Ry],
+            vec![HatType::Discrete],
+        );
+        let full =
+            DeviceCapabilities::new(2, 128, AxisKind::ALL.to_vec(), vec![HatType::Continuous; 4]);
+        let wheel = DeviceCapabilities::new(
+            3,
+            24,
+            vec![AxisKind::X, AxisKind::Z, AxisKind::Rz],
+            vec![HatType::Continuous],
+        );
+        let busy_wheel = DeviceCapabilities::new(
+            4,
+            24,
+            vec![AxisKind::X, AxisKind::Z, AxisKind::Rz],
+            vec![HatType::Continuous],
+        );
+
+        vec![
+            (gamepad, DeviceStatus::Owned),
+            (full, DeviceStatus::Free),
+            (wheel, DeviceStatus::Owned),
+            (busy_wheel, DeviceStatus::Busy),
+        ]
+    }
+
+    fn ids(query: &DeviceQuery) -> Vec<u32> {
+        query
+            .rank(candidates())
+            .iter()
+            .map(|candidate| candidate.device_id())
+            .collect()
+    }
+
+    #[test]
+    fn match_requirements() {
+        assert_eq!(ids(&DeviceQuery::new()), vec![3, 1, 2]);
+
+        let wheel = DeviceQuery::new().axes(&[AxisKind::X, AxisKind::Z, AxisKind::Rz]);
+        assert_eq!(ids(&wheel), vec![3, 2]);
+        assert_eq!(ids(&wheel.clone().buttons(..=32)), vec![3]);
+        assert_eq!(ids(&wheel.clone().status(DeviceStatus::Busy)), vec![4]);
+        assert_eq!(
+            ids(&wheel.status(DeviceStatus::Busy).status(DeviceStatus::Owned)),
+            vec![3, 4]
+        );
+
+        assert_eq!(
+            ids(&DeviceQuery::new().hat_type(HatType::Discrete)),
+            vec![1]
+        );
+        assert_eq!(ids(&DeviceQuery::new().hats(2..)), vec![2]);
+        assert_eq!(ids(&DeviceQuery::new().buttons(17..128)), vec![3]);
+        assert!(ids(&DeviceQuery::new().buttons(129..)).is_empty());
+    }
+
+    #[test]
+    fn rank_closest_fit() {
+        let query = DeviceQuery::new()
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .buttons(8..);
+        let matches = query.rank(candidates());
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].device_id(), 1);
+        assert_eq!(matches[0].status(), DeviceStatus::Owned);
+        assert_eq!(matches[1].device_id(), 2);
+        assert_eq!(matches[1].capabilities().num_axes(), 8);
+
+        assert_eq!(
+            query.to_string(),
+            "buttons: 8..=128 | axes: [X, Y] | hats: 0..=4"
+        );
+        assert_eq!(
+            matches[0].to_string(),
+            "Device ID: 1 | buttons: 16 | axes: [X, Y, Rx, Ry] | hats: [discrete] | status: owned"
+        );
+    }
+
+    #[test]
+    fn owned_ids_with_gaps() {
+        // Devices 1 and 3 could not be acquired, so the owned devices are not indexed by ID
+        let owned = [
+            DeviceCapabilities::new(2, 8, vec![AxisKind::X], vec![]),
+            DeviceCapabilities::new(4, 32, vec![AxisKind::X, AxisKind::Z], vec![]),
+        ];
+        let candidates = owned
+            .iter()
+            .map(|capabilities| (capabilities.clone(), DeviceStatus::Owned));
+
+        let matches = DeviceQuery::new().axis(AxisKind::Z).rank(candidates);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].device_id(), 4);
+        assert_eq!(matches[0].capabilities(), &owned[1]);
+    }
+}
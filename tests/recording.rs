@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::{
+        AppError, AxisKind, ButtonState, Device, DeviceBuilder, Error, FourWayHat, HatState,
+        HatType, Recorder, Recording,
+    };
+
+    fn device() -> Device {
+        let mut device = DeviceBuilder::new(2)
+            .buttons(4)
+            .axes(&[AxisKind::Dial, AxisKind::X])
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+        device.set_button(2, ButtonState::Pressed).unwrap();
+        device.set_axis(1, 0x4000).unwrap();
+        device.set_axis(2, -5).unwrap();
+        device
+            .set_hat(1, HatState::Discrete(FourWayHat::West))
+            .unwrap();
+        device
+    }
+
+    #[test]
+    fn parse_recording() {
+        let mut recording = Recording::new();
+        recording.push(Duration::from_micros(1500), device());
+        recording.push(
+            Duration::from_millis(20),
+            DeviceBuilder::new(3).build().unwrap(),
+        );
+
+        let mut text = Vec::new();
+        recording.write_to(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(
+            text,
+            "# vjoy recording v1\n1500 2 0100 X=16384,Dial/Slider2=-5 d:W,c:-\n20000 3 - - -\n"
+        );
+
+        let parsed = Recording::read_from(text.as_bytes()).unwrap();
+        assert_eq!(parsed, recording);
+        assert_eq!(parsed.duration(), Duration::from_millis(20));
+
+        for line in [
+            "1500 2 0100 X=16384",
+            "1500 2 0120 - -",
+            "1500 2 - X=1,X=2 -",
+            "1500 2 - Throttle=1 -",
+            "1500 2 - 0x40=1,0x40=2 -",
+            "1500 2 - 0xZ=1 -",
+            "1500 2 - - c:36000",
+            "1500 17 - - -",
+        ] {
+            let text = format!("# vjoy recording v1\n{}", line);
+            assert!(matches!(
+                Recording::read_from(text.as_bytes()),
+                Err(Error::App(AppError::InvalidRecordingLine(2, _)))
+            ));
+        }
+        assert!(matches!(
+            Recording::read_from("time,x\n0,1\n".as_bytes()),
+            Err(Error::App(AppError::InvalidRecordingLine(1, _)))
+        ));
+    }
+
+    #[test]
+    fn unknown_axes() {
+        let text = "# vjoy recording v1\n0 1 - 0x40=7,Y=3,0x35=5 -\n";
+        let recording = Recording::read_from(text.as_bytes()).unwrap();
+        let axes: Vec<(Option<AxisKind>, i32)> = recording.states[0]
+            .device
+            .axes()
+            .map(|axis| (axis.kind(), axis.get()))
+            .collect();
+        assert_eq!(
+            axes,
+            vec![(Some(AxisKind::Y), 3), (Some(AxisKind::Rz), 5), (None, 7)]
+        );
+
+        let mut written = Vec::new();
+        recording.write_to(&mut written).unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "# vjoy recording v1\n0 1 - Y=3,Rz=5,0x40=7 -\n"
+        );
+    }
+
+    #[test]
+    fn rotate_recordings() {
+        let directory = std::env::temp_dir().join(format!("vjoy-recording-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("flight.rec");
+
+        let device = device();
+        let mut recorder = Recorder::create(&path).unwrap().rotate(120, 2);
+        for index in 0..10 {
+            recorder
+                .record_at(Duration::from_millis(index), &device)
+                .unwrap();
+            assert!(recorder.file_size() <= 120);
+        }
+        recorder.flush().unwrap();
+
+        let timestamps = |path| {
+            Recording::load(path)
+                .unwrap()
+                .states
+                .iter()
+                .map(|state| state.timestamp.as_millis())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timestamps(recorder.rotated_path(2)), vec![4, 5]);
+        assert_eq!(timestamps(recorder.rotated_path(1)), vec![6, 7]);
+        assert_eq!(timestamps(path), vec![8, 9]);
+        assert!(!recorder.rotated_path(3).exists());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::{
+        AppError, AxisKind, Device, DeviceBuilder, DeviceSink, Error, MemorySink, Recording,
+    };
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn state(device_id: u32, x: i32) -> Device {
+        let mut device = DeviceBuilder::new(device_id)
+            .axis(AxisKind::X)
+            .build()
+            .unwrap();
+        device.set_axis(1, x).unwrap();
+        device
+    }
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new();
+        recording.push(ms(0), state(1, 0));
+        recording.push(ms(0), state(2, 0));
+        recording.push(ms(100), state(1, 100));
+        recording.push(ms(200), state(2, 200));
+        recording.push(ms(300), state(1, 300));
+        recording
+    }
+
+    fn x(device: &Device) -> i32 {
+        device.axes().next().unwrap().get()
+    }
+
+    #[test]
+    fn replay_timing() {
+        let recording = recording();
+        let mut sink = MemorySink::new();
+        let mut replayer = recording.replayer().speed(2.0).remap(2, 5);
+
+        assert_eq!(replayer.advance(&mut sink, ms(0)).unwrap(), 2);
+        assert_eq!(replayer.advance(&mut sink, ms(49)).unwrap(), 0);
+        assert_eq!(replayer.advance(&mut sink, ms(1)).unwrap(), 1);
+        assert_eq!(replayer.position(), ms(100));
+        assert_eq!(replayer.next_timestamp(), Some(ms(200)));
+        assert_eq!(replayer.advance(&mut sink, ms(100)).unwrap(), 2);
+        assert!(replayer.is_finished());
+
+        let ids: Vec<u32> = sink.sent().iter().map(|device| device.id()).collect();
+        assert_eq!(ids, vec![1, 5, 1, 5, 1]);
+        assert_eq!(x(sink.device(1).unwrap()), 300);
+        assert_eq!(x(sink.device(5).unwrap()), 200);
+        assert!(sink.device(2).is_none());
+    }
+
+    #[test]
+    fn seek_and_loop() {
+        let recording = recording();
+        let mut sink = MemorySink::new();
+        let mut replayer = recording.replayer().looping(true);
+
+        // Catch up on the last state of each device before the new position
+        replayer.seek(ms(250));
+        assert_eq!(replayer.advance(&mut sink, ms(0)).unwrap(), 2);
+        assert_eq!(x(sink.device(1).unwrap()), 100);
+        assert_eq!(x(sink.device(2).unwrap()), 200);
+
+        // 300 ms state, then wrap to 0 and 100 ms states
+        sink.clear();
+        assert_eq!(replayer.advance(&mut sink, ms(160)).unwrap(), 4);
+        assert_eq!(replayer.position(), ms(110));
+        let xs: Vec<i32> = sink.sent().iter().map(x).collect();
+        assert_eq!(xs, vec![300, 0, 0, 100]);
+        assert!(!replayer.is_finished());
+
+        replayer.rewind();
+        assert_eq!(replayer.position(), Duration::ZERO);
+        assert_eq!(replayer.next_timestamp(), Some(ms(100)));
+    }
+
+    #[test]
+    fn replay_sink_errors() {
+        let recording = recording();
+        let mut sent = Vec::new();
+        let mut sink = |device: &Device| {
+            if device.id() == 2 {
+                return Err(Error::App(AppError::DeviceNotFound(2)));
+            }
+            sent.push(x(device));
+            Ok(())
+        };
+
+        let mut replayer = recording.replayer();
+        assert!(replayer.advance(&mut sink, ms(300)).is_err());
+        assert!(replayer.advance(&mut sink, ms(0)).is_err());
+        assert_eq!(replayer.advance(&mut sink, ms(0)).unwrap(), 1);
+        assert!(replayer.is_finished());
+        assert_eq!(sent, vec![0, 100, 300]);
+
+        let mut memory = MemorySink::new();
+        memory.send(&state(1, 7)).unwrap();
+        assert!(!memory.device(1).unwrap().is_dirty());
+    }
+
+    #[test]
+    fn loop_without_length() {
+        let empty = Recording::new();
+        let mut sink = MemorySink::new();
+        let mut replayer = empty.replayer().looping(true);
+        assert!(replayer.is_finished());
+        replayer.play(&mut sink).unwrap();
+        assert!(sink.sent().is_empty());
+
+        let mut instant = Recording::new();
+        instant.push(ms(0), state(1, 10));
+        instant.push(ms(0), state(2, 20));
+        let mut replayer = instant.replayer().looping(true);
+        assert!(!replayer.is_finished());
+        replayer.play(&mut sink).unwrap();
+        assert!(replayer.is_finished());
+        assert_eq!(sink.sent().len(), 2);
+    }
+}
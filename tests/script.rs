@@ -0,0 +1,272 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::script::{Command, Interpreter, Script};
+    use vjoy::{
+        AppError, Axis, AxisKind, ButtonState, Device, DeviceBuilder, Error, FourWayHat, HatAngle,
+        HatState, HatType, MemorySink,
+    };
+
+    const EXAMPLE: &str =
+        "dev 1: press b3; hold 80ms; axis X 0.5 over 200ms; hat 1 east; wait 1s; release all";
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn device(device_id: u32) -> Device {
+        DeviceBuilder::new(device_id)
+            .buttons(8)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap()
+    }
+
+    fn x(device: &Device) -> i32 {
+        device.axes().next().unwrap().get()
+    }
+
+    fn button(device: &Device, button_id: usize) -> ButtonState {
+        device.buttons().nth(button_id - 1).unwrap().get()
+    }
+
+    fn invalid_line(source: &str) -> usize {
+        match Script::parse(source) {
+            Err(Error::App(AppError::InvalidScript(line, _))) => line,
+            other => panic!("expected an invalid script, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_script() {
+        let script = Script::parse(EXAMPLE).unwrap();
+        let commands: Vec<&Command> = script
+            .statements
+            .iter()
+            .map(|statement| &statement.command)
+            .collect();
+
+        assert_eq!(
+            commands,
+            vec![
+                &Command::Device(1),
+                &Command::Press(vec![3]),
+                &Command::Wait(ms(80)),
+                &Command::Axis {
+                    kind: AxisKind::X,
+                    value: 0.5,
+                    over: Some(ms(200)),
+                },
+                &Command::Hat {
+                    hat_id: 1,
+                    direction: Some(HatAngle::from_hundredths(9000).unwrap()),
+                },
+                &Command::Wait(ms(1000)),
+                &Command::ReleaseAll,
+            ]
+        );
+
+        let source = "# warm up\nDEV 2 :\n  press b1 b2 # both\n\nhat 2 225deg; axis slider2 -1\nwait 1.5s; hat 1 c; reset";
+        let script: Script = source.parse().unwrap();
+        let lines: Vec<usize> = script.statements.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![2, 3, 5, 5, 6, 6, 6]);
+        assert_eq!(script.statements[1].command, Command::Press(vec![1, 2]));
+        assert_eq!(
+            script.statements[3].command,
+            Command::Axis {
+                kind: AxisKind::Dial,
+                value: -1.0,
+                over: None,
+            }
+        );
+        assert_eq!(script.statements[4].command, Command::Wait(ms(1500)));
+        assert_eq!(
+            script.statements[5].command,
+            Command::Hat {
+                hat_id: 1,
+                direction: None,
+            }
+        );
+
+        // Display prints the canonical syntax, which parses to the same script
+        assert_eq!(
+            script.to_string(),
+            "dev 2:\npress b1 b2\nhat 2 225deg\naxis Dial/Slider2 -1\nwait 1500ms\nhat 1 center\nreset\n"
+        );
+        assert_eq!(
+            Script::parse(&script.to_string()).unwrap().to_string(),
+            script.to_string()
+        );
+        assert_eq!(
+            Command::Wait(Duration::from_micros(1500)).to_string(),
+            "wait 1500us"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(invalid_line("press b1"), 1);
+        assert_eq!(invalid_line("dev 1:\njump"), 2);
+        assert_eq!(invalid_line("dev 1:\n\npress 3"), 3);
+        assert_eq!(invalid_line("dev 1: axis X 1.5"), 1);
+        assert_eq!(invalid_line("dev 1: axis W 0.5"), 1);
+        assert_eq!(invalid_line("dev 1: axis X 0.5 in 1s"), 1);
+        assert_eq!(invalid_line("dev 1: hat 1 up"), 1);
+        assert_eq!(invalid_line("dev 1: hat 1 360deg"), 1);
+        assert_eq!(invalid_line("dev 1: wait 10"), 1);
+        assert_eq!(invalid_line("dev 1: wait -1s"), 1);
+        assert_eq!(invalid_line("dev 1: reset now"), 1);
+        assert_eq!(invalid_line("dev x: reset"), 1);
+
+        let Err(Error::App(error)) = Script::parse("dev 1:\nhold 1m") else {
+            panic!("expected an invalid script");
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid script line 2: duration needs a unit of us, ms or s."
+        );
+    }
+
+    #[test]
+    fn interpreter_timing() {
+        let script = Script::parse(EXAMPLE).unwrap();
+        let mut sink = MemorySink::new();
+        let mut interpreter = Interpreter::new(&script).device(device(1));
+
+        // Press is sent before the hold
+        assert_eq!(interpreter.advance(&mut sink, ms(0)).unwrap(), 1);
+        assert_eq!(button(sink.device(1).unwrap(), 3), ButtonState::Pressed);
+        assert_eq!(interpreter.advance(&mut sink, ms(79)).unwrap(), 0);
+
+        // The axis move starts after the hold and is interpolated on each step
+        assert_eq!(interpreter.advance(&mut sink, ms(1)).unwrap(), 1);
+        assert_eq!(x(sink.device(1).unwrap()), 0);
+        interpreter.advance(&mut sink, ms(100)).unwrap();
+        assert_eq!(x(sink.device(1).unwrap()), Axis::from_normalized(0.5) / 2);
+
+        // The hat is set once the move has finished
+        interpreter.advance(&mut sink, ms(99)).unwrap();
+        assert_eq!(
+            sink.device(1).unwrap().hats().next().unwrap().get(),
+            HatState::Discrete(FourWayHat::Centered)
+        );
+        interpreter.advance(&mut sink, ms(1)).unwrap();
+        let state = sink.device(1).unwrap();
+        assert_eq!(x(state), Axis::from_normalized(0.5));
+        assert_eq!(
+            state.hats().next().unwrap().get(),
+            HatState::Discrete(FourWayHat::East)
+        );
+        assert_eq!(interpreter.time(), ms(280));
+        assert!(!interpreter.is_finished());
+
+        interpreter.advance(&mut sink, ms(999)).unwrap();
+        assert_eq!(button(sink.device(1).unwrap(), 3), ButtonState::Pressed);
+        assert_eq!(interpreter.advance(&mut sink, ms(1)).unwrap(), 1);
+        assert_eq!(button(sink.device(1).unwrap(), 3), ButtonState::Released);
+        assert!(interpreter.is_finished());
+        assert_eq!(interpreter.state(1), sink.device(1));
+    }
+
+    #[test]
+    fn interpreter_run() {
+        let script = Script::parse(
+            "dev 1: press b1; axis Y -1; hat 2 northeast\ndev 2: hat 1 southwest; wait 50ms; hat 2 90deg\ndev 1: reset",
+        )
+        .unwrap();
+        let mut sink = MemorySink::new();
+        let mut interpreter = Interpreter::new(&script)
+            .device(device(1))
+            .device(device(2));
+        interpreter.run(&mut sink).unwrap();
+
+        assert!(interpreter.is_finished());
+        assert_eq!(interpreter.time(), ms(50));
+        let ids: Vec<u32> = sink.sent().iter().map(|device| device.id()).collect();
+        assert_eq!(ids, vec![1, 2, 1, 2]);
+
+        let first = &sink.sent()[0];
+        assert_eq!(button(first, 1), ButtonState::Pressed);
+        assert_eq!(first.axes().nth(1).unwrap().get(), Axis::MIN);
+        assert_eq!(
+            first.hats().nth(1).unwrap().get(),
+            HatState::Continuous(4500)
+        );
+
+        // Discrete hats snap to the nearest 4-way direction
+        let hats: Vec<HatState> = sink
+            .device(2)
+            .unwrap()
+            .hats()
+            .map(|hat| hat.get())
+            .collect();
+        assert_eq!(
+            hats,
+            vec![
+                HatState::Discrete(FourWayHat::West),
+                HatState::Continuous(9000)
+            ]
+        );
+
+        // Reset centers X and Y like the driver instead of deflecting them fully
+        let reset = sink.device(1).unwrap();
+        let axes: Vec<i32> = reset.axes().map(|axis| axis.get()).collect();
+        assert_eq!(axes, vec![Axis::CENTER, Axis::CENTER]);
+        assert_eq!(button(reset, 1), ButtonState::Released);
+        assert_eq!(
+            reset.hats().nth(1).unwrap().get(),
+            HatState::Continuous(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn trailing_hold() {
+        let script = Script::parse("dev 1: press b1; hold 80ms").unwrap();
+        let mut sink = MemorySink::new();
+        let mut interpreter = Interpreter::new(&script).device(device(1));
+
+        assert_eq!(interpreter.advance(&mut sink, ms(0)).unwrap(), 1);
+        assert!(!interpreter.is_finished());
+        interpreter.advance(&mut sink, ms(79)).unwrap();
+        assert!(!interpreter.is_finished());
+        interpreter.advance(&mut sink, ms(1)).unwrap();
+        assert!(interpreter.is_finished());
+
+        let mut interpreter = Interpreter::new(&script).device(device(1));
+        interpreter.run(&mut sink).unwrap();
+        assert_eq!(interpreter.time(), ms(80));
+    }
+
+    #[test]
+    fn interpreter_errors() {
+        let script = Script::parse("dev 3: press b1").unwrap();
+        let mut interpreter = Interpreter::new(&script).device(device(1));
+        assert!(matches!(
+            interpreter.run(&mut MemorySink::new()),
+            Err(Error::App(AppError::DeviceNotFound(3)))
+        ));
+
+        let script =
+            Script::parse("dev 1: press b9; hat 3 north; axis Z 0.1 over 1s; press b2").unwrap();
+        let mut sink = MemorySink::new();
+        let mut interpreter = Interpreter::new(&script).device(device(1));
+        assert!(matches!(
+            interpreter.advance(&mut sink, ms(0)),
+            Err(Error::App(AppError::ButtonNotFound(1, 9)))
+        ));
+        assert!(matches!(
+            interpreter.advance(&mut sink, ms(0)),
+            Err(Error::App(AppError::HatNotFound(1, 3)))
+        ));
+        assert!(matches!(
+            interpreter.advance(&mut sink, ms(0)),
+            Err(Error::App(AppError::AxisNotFound(1, 3)))
+        ));
+
+        // The script continues after the failed commands
+        interpreter.run(&mut sink).unwrap();
+        assert_eq!(button(sink.device(1).unwrap(), 2), ButtonState::Pressed);
+    }
+}
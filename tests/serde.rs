@@ -0,0 +1,47 @@
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use vjoy::{ButtonState, Device, EightWayHat, FourWayHat, HatAngle, HatState, VJoySnapshot};
+
+    #[test]
+    fn serialize_states() {
+        let states = vec![
+            HatState::Discrete(FourWayHat::West),
+            HatState::from(EightWayHat::SouthEast),
+            HatState::Continuous(u32::MAX),
+        ];
+        let json = serde_json::to_string(&states).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Vec<HatState>>(&json).unwrap(),
+            states
+        );
+
+        let json = serde_json::to_string(&ButtonState::Pressed).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ButtonState>(&json).unwrap(),
+            ButtonState::Pressed
+        );
+    }
+
+    #[test]
+    fn reject_invalid_hat_angle() {
+        assert_eq!(serde_json::to_string(&HatAngle::WEST).unwrap(), "27000");
+        assert_eq!(
+            serde_json::from_str::<HatAngle>("9000").unwrap(),
+            HatAngle::EAST
+        );
+        assert!(serde_json::from_str::<HatAngle>("36000").is_err());
+    }
+
+    #[test]
+    fn serialize_snapshot() {
+        let snapshot = VJoySnapshot {
+            devices: vec![Device::default()],
+        };
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("dirty"));
+
+        let restored: VJoySnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, snapshot);
+        assert!(!restored.devices[0].is_dirty());
+    }
+}
@@ -1,9 +1,9 @@
-#[cfg(test)]
+#[cfg(all(test, target_os = "windows"))]
 
 mod tests {
     // The process by which devices are acquired from the C API is not compatible with Rust's test harness and only works within the same test.
     // The tests can be monitored via the vJoyMonitor and vJoyList executables bundled with vJoy.
-    use vjoy::{ButtonState, FourWayHat, HatState, VJoy};
+    use vjoy::{ButtonState, FourWayHat, HatState, HatType, VJoy};
 
     #[test]
     fn test() {
@@ -11,11 +11,11 @@ mod tests {
         let mut device_1 = vjoy.get_device_state(1).unwrap();
 
         let (hat_set, hat_reset) = match device_1.hat_type() {
-            HatState::Discrete(_) => (
+            HatType::Discrete => (
                 HatState::Discrete(FourWayHat::East),
                 HatState::Discrete(FourWayHat::Centered),
             ),
-            HatState::Continuous(_) => (
+            HatType::Continuous => (
                 HatState::Continuous(90 * 100),
                 HatState::Continuous(u32::MAX),
             ),
@@ -78,6 +78,70 @@ mod tests {
         vjoy.update_all_devices().unwrap();
         // Simple test for 1 ref mut device
 
+        // Dirty tracking for 1 ref mut device
+        assert!(!vjoy.get_device_state_ref(1).unwrap().is_dirty());
+        assert!(vjoy.pending_diff(1).unwrap().is_empty());
+
+        let device_1_mut = vjoy.get_device_state_mut(1).unwrap();
+        device_1_mut.set_button(1, ButtonState::Pressed).unwrap();
+        assert!(device_1_mut.is_dirty());
+        assert_eq!(
+            vjoy.pending_diff(1).unwrap().buttons,
+            vec![(1, ButtonState::Pressed)]
+        );
+        vjoy.update_all_devices().unwrap();
+        assert!(vjoy.pending_diff(1).unwrap().is_empty());
+        // Dirty tracking for 1 ref mut device
+
+        // Transaction for 1 device
+        vjoy.transaction(1, |device| device.set_button(2, ButtonState::Pressed))
+            .unwrap();
+        assert_eq!(
+            vjoy.get_device_state_ref(1)
+                .unwrap()
+                .buttons()
+                .nth(1)
+                .unwrap()
+                .get(),
+            ButtonState::Pressed
+        );
+        assert!(
+            vjoy.transaction(1, |device| {
+                device.set_button(2, ButtonState::Released)?;
+                device.set_button(u8::MAX, ButtonState::Pressed)
+            })
+            .is_err()
+        );
+        assert_eq!(
+            vjoy.get_device_state_ref(1)
+                .unwrap()
+                .buttons()
+                .nth(1)
+                .unwrap()
+                .get(),
+            ButtonState::Pressed
+        );
+        // Transaction for 1 device
+
+        // Driver resets for 1 device
+        vjoy.get_device_state_mut(1)
+            .unwrap()
+            .set_button(1, ButtonState::Pressed)
+            .unwrap();
+        vjoy.update_all_devices().unwrap();
+        vjoy.reset_buttons(1).unwrap();
+        assert!(
+            vjoy.get_device_state_ref(1)
+                .unwrap()
+                .buttons()
+                .all(|button| button.get() == ButtonState::Released)
+        );
+        vjoy.reset_hats(1).unwrap();
+        vjoy.reset_device(1).unwrap();
+        assert!(vjoy.pending_diff(1).unwrap().is_empty());
+        vjoy.reset_all_devices();
+        // Driver resets for 1 device
+
         // Test all buttons/axes/hats for 1 device
         println!("Setting all buttons for device 1");
         for button in device_1.buttons_mut() {
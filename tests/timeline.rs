@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use vjoy::{
+        AppError, AxisKind, ButtonState, Device, DeviceBuilder, Error, FourWayHat, HatState,
+        HatType, Recording,
+    };
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn stick() -> Device {
+        let mut device = DeviceBuilder::new(1)
+            .buttons(2)
+            .axes(&[AxisKind::X, AxisKind::Y])
+            .hat(HatType::Discrete)
+            .build()
+            .unwrap();
+        device.set_button(2, ButtonState::Pressed).unwrap();
+        device.set_axis(1, 16384).unwrap();
+        device
+            .set_hat(1, HatState::Discrete(FourWayHat::East))
+            .unwrap();
+        device
+    }
+
+    fn pedals() -> Device {
+        let mut device = DeviceBuilder::new(2)
+            .axis(AxisKind::Rz)
+            .hat(HatType::Continuous)
+            .hat(HatType::Continuous)
+            .build()
+            .unwrap();
+        device.set_axis(1, 300).unwrap();
+        device.set_hat(1, HatState::Continuous(4500)).unwrap();
+        device
+    }
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new();
+        recording.push(ms(0), stick());
+        recording.push(ms(1), pedals());
+        recording.push(Duration::from_micros(1500), stick());
+        recording
+    }
+
+    fn invalid_row(csv: &str) -> usize {
+        match Recording::read_csv(csv.as_bytes()) {
+            Err(Error::App(AppError::InvalidTimelineRow(row, _))) => row,
+            other => panic!("expected an invalid timeline, got {:?}", other),
+        }
+    }
+
+    const CSV: &str = "time_us,device,b1,b2,X,Y,Rz,hat1,hat2
+0,1,0,1,16384,0,,E,
+1000,2,,,,,300,4500,-1
+1500,1,0,1,16384,0,,E,
+";
+
+    #[test]
+    fn csv_export_import() {
+        let recording = recording();
+        let mut csv = Vec::new();
+        recording.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), CSV);
+
+        assert_eq!(Recording::read_csv(CSV.as_bytes()).unwrap(), recording);
+    }
+
+    #[test]
+    fn csv_hand_authored() {
+        let csv = "# approach
+Y, hat1 ,device,time_us,b3
+
+16384,n,1,0,1
+,,1,80000,
+";
+        let recording = Recording::read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(recording.states.len(), 2);
+        assert_eq!(recording.duration(), ms(80));
+
+        let first = &recording.states[0].device;
+        let buttons: Vec<ButtonState> = first.buttons().map(|button| button.get()).collect();
+        assert_eq!(
+            buttons,
+            vec![
+                ButtonState::Released,
+                ButtonState::Released,
+                ButtonState::Pressed
+            ]
+        );
+        assert_eq!(first.axes().next().unwrap().kind(), Some(AxisKind::Y));
+        assert_eq!(first.axes().next().unwrap().get(), 16384);
+        assert_eq!(
+            first.hats().next().unwrap().get(),
+            HatState::Discrete(FourWayHat::North)
+        );
+
+        let second = &recording.states[1].device;
+        assert_eq!(
+            (second.num_buttons(), second.num_axes(), second.num_hats()),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn csv_errors() {
+        assert_eq!(invalid_row("time_us,device,b1,Throttle\n0,1,0,1"), 1);
+        assert_eq!(invalid_row("time_us,device,X,x\n0,1,0,1"), 1);
+        assert_eq!(invalid_row("time_us,device,b1\n0,1,0\n1,1"), 3);
+        assert_eq!(invalid_row("time_us,device,b1\n\n0,1,2"), 3);
+        assert_eq!(invalid_row("time_us,device,hat1\n0,1,36000"), 2);
+        assert_eq!(invalid_row("time_us,device,hat2\n0,1,N"), 2);
+        assert_eq!(invalid_row("device,b1\n1,0"), 2);
+        assert_eq!(invalid_row("time_us,device\n0,17"), 2);
+
+        let Err(Error::App(error)) = Recording::read_csv("time_us,b1\n0,1".as_bytes()) else {
+            panic!("expected an invalid timeline");
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid row 2 in timeline: missing device."
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_lines_export_import() {
+        let recording = recording();
+        let mut json = Vec::new();
+        recording.write_json_lines(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(
+            json.lines().collect::<Vec<_>>(),
+            vec![
+                r#"{"time_us":0,"device":1,"b1":0,"b2":1,"X":16384,"Y":0,"hat1":"E"}"#,
+                r#"{"time_us":1000,"device":2,"Rz":300,"hat1":4500,"hat2":-1}"#,
+                r#"{"time_us":1500,"device":1,"b1":0,"b2":1,"X":16384,"Y":0,"hat1":"E"}"#,
+            ]
+        );
+
+        assert_eq!(
+            Recording::read_json_lines(json.as_bytes()).unwrap(),
+            recording
+        );
+
+        let hand_authored =
+            "{\"device\": 1, \"time_us\": 0, \"b1\": 1, \"x\": 0, \"hat1\": null}\n";
+        let recording = Recording::read_json_lines(hand_authored.as_bytes()).unwrap();
+        assert_eq!(recording.states[0].device.num_hats(), 0);
+        assert_eq!(recording.states[0].device.num_buttons(), 1);
+
+        assert!(matches!(
+            Recording::read_json_lines("{\"device\": 1, \"time_us\": [0]}".as_bytes()),
+            Err(Error::App(AppError::InvalidTimelineRow(1, _)))
+        ));
+        assert!(matches!(
+            Recording::read_json_lines("\n{\"device\": 1".as_bytes()),
+            Err(Error::App(AppError::InvalidTimelineRow(2, _)))
+        ));
+    }
+}